@@ -1,22 +1,135 @@
-use string_width::string_width;
+use std::borrow::Cow;
+
+use string_width::{ansi_sequence_len, string_width, truncate_ansi, WidthCache, WidthStrategy};
 
 /// Alignment options for text
+///
+/// More alignments may be added in future releases, so matches outside this
+/// crate need a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Alignment {
     Left,
     Center,
     Right,
+    /// Right-align lines that look like numbers and left-align the rest,
+    /// for mixed output such as labels and totals. This is a heuristic; see
+    /// [`Alignment::resolve`]
+    Smart,
+}
+
+impl Alignment {
+    /// The alignment to use for `line`: itself, unless this is `Smart`.
+    ///
+    /// `Smart` picks `Right` when the visible text, trimmed, parses as a
+    /// number (`42`, `-3.5`, `1e6`) and `Left` otherwise. Thousands
+    /// separators, currency signs and units are not recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ansi_align::Alignment;
+    ///
+    /// assert_eq!(Alignment::Smart.resolve(" 42 "), Alignment::Right);
+    /// assert_eq!(Alignment::Smart.resolve("total"), Alignment::Left);
+    /// assert_eq!(Alignment::Center.resolve("42"), Alignment::Center);
+    /// ```
+    pub fn resolve(self, line: &str) -> Alignment {
+        match self {
+            Alignment::Smart if looks_numeric(line) => Alignment::Right,
+            Alignment::Smart => Alignment::Left,
+            align => align,
+        }
+    }
+}
+
+/// Whether the visible text of `line` is a number. `f64` parsing alone
+/// would also accept words like `inf` and `NaN`, so a digit is required.
+fn looks_numeric(line: &str) -> bool {
+    let mut visible = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('\u{001B}') {
+        visible.push_str(&rest[..start]);
+        rest = &rest[start + ansi_sequence_len(&rest[start..])..];
+    }
+    visible.push_str(rest);
+
+    let visible = visible.trim();
+    visible.bytes().any(|b| b.is_ascii_digit()) && visible.parse::<f64>().is_ok()
+}
+
+/// The error returned when parsing an unknown alignment name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAlignmentError(String);
+
+impl std::fmt::Display for ParseAlignmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown alignment `{}`, expected left, center, right or smart", self.0)
+    }
+}
+
+impl std::error::Error for ParseAlignmentError {}
+
+impl std::str::FromStr for Alignment {
+    type Err = ParseAlignmentError;
+
+    /// Parse `left`, `center`, `right` or `smart`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "left" => Ok(Alignment::Left),
+            "center" => Ok(Alignment::Center),
+            "right" => Ok(Alignment::Right),
+            "smart" => Ok(Alignment::Smart),
+            _ => Err(ParseAlignmentError(s.to_string())),
+        }
+    }
+}
+
+/// Which side receives the extra column when a centered line's gap is odd
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CenterBias {
+    /// The line sits one column left of true center (extra pad on the right)
+    #[default]
+    Left,
+    /// The line sits one column right of true center (extra pad on the left)
+    Right,
 }
 
 /// Options for text alignment
 #[derive(Debug, Clone)]
 pub struct AlignOptions {
-    /// The alignment type (left, center, right)
+    /// The alignment type (left, center, right or smart)
     pub align: Alignment,
-    /// The string to split lines on (default: "\n")
+    /// The string to split lines on; an empty string falls back to "\n"
+    /// (default: "\n")
     pub split: String,
     /// The padding character to use (default: " ")
     pub pad: char,
+    /// A padding unit of any width, repeated in place of `pad`; the unit
+    /// nearest the content is clipped so the padding is exactly as wide as
+    /// needed (default: None)
+    pub pad_str: Option<String>,
+    /// Whether `try_ansi_align` should reject lines containing cursor-movement
+    /// sequences instead of silently leaving them unpadded (default: false)
+    pub strict_cursor: bool,
+    /// Pad lines on both sides so every line reaches the full width (default: false)
+    pub pad_both: bool,
+    /// Where the odd column goes when centering (default: left)
+    pub center_bias: CenterBias,
+    /// A minimum width to align against, in place of the widest line when wider
+    pub width: Option<usize>,
+    /// Stretch every line but the last to the full width by widening the
+    /// spaces between words; `align` is ignored (default: false)
+    pub justify: bool,
+    /// How the last line is aligned in justify mode (default: left)
+    pub last_line_align: Option<Alignment>,
+    /// Align against the detected terminal width instead of `width`
+    /// (default: false)
+    pub to_terminal: bool,
+    /// Put leading padding before a line's opening escape sequences, so a
+    /// leading color covers only the text; when false the padding goes after
+    /// them and is colored too (default: true)
+    pub pad_before_ansi: bool,
 }
 
 impl Default for AlignOptions {
@@ -25,11 +138,29 @@ impl Default for AlignOptions {
             align: Alignment::Center,
             split: "\n".to_string(),
             pad: ' ',
+            pad_str: None,
+            strict_cursor: false,
+            pad_both: false,
+            center_bias: CenterBias::default(),
+            width: None,
+            justify: false,
+            last_line_align: None,
+            to_terminal: false,
+            pad_before_ansi: true,
         }
     }
 }
 
 impl AlignOptions {
+    /// The string lines are split on, with an empty `split` meaning `"\n"`
+    /// rather than a split between every character
+    fn line_separator(&self) -> &str {
+        match self.split.as_str() {
+            "" => "\n",
+            split => split,
+        }
+    }
+
     /// Create new options with specified alignment
     pub fn new(align: Alignment) -> Self {
         Self {
@@ -49,10 +180,144 @@ impl AlignOptions {
         self.pad = pad;
         self
     }
+
+    /// Set a repeating padding unit, which takes precedence over `pad`
+    pub fn pad_str<S: Into<String>>(mut self, pad_str: S) -> Self {
+        self.pad_str = Some(pad_str.into());
+        self
+    }
+
+    /// Set whether cursor-movement lines are an error in `try_ansi_align`
+    pub fn strict_cursor(mut self, strict_cursor: bool) -> Self {
+        self.strict_cursor = strict_cursor;
+        self
+    }
+
+    /// Set whether lines are padded on both sides to the full width
+    pub fn pad_both(mut self, pad_both: bool) -> Self {
+        self.pad_both = pad_both;
+        self
+    }
+
+    /// Set where the odd column goes when centering
+    pub fn center_bias(mut self, center_bias: CenterBias) -> Self {
+        self.center_bias = center_bias;
+        self
+    }
+
+    /// Set the minimum width to align against
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Set whether lines are justified to the full width
+    pub fn justify(mut self, justify: bool) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Set how the last line is aligned in justify mode
+    pub fn last_line_align(mut self, last_line_align: Alignment) -> Self {
+        self.last_line_align = Some(last_line_align);
+        self
+    }
+
+    /// Set whether lines are aligned across the whole terminal
+    pub fn to_terminal(mut self, to_terminal: bool) -> Self {
+        self.to_terminal = to_terminal;
+        self
+    }
+
+    /// Set whether leading padding goes before a line's opening escapes
+    pub fn pad_before_ansi(mut self, pad_before_ansi: bool) -> Self {
+        self.pad_before_ansi = pad_before_ansi;
+        self
+    }
+}
+
+/// Detect the terminal width from the `COLUMNS` environment variable,
+/// falling back to 80 columns.
+pub fn terminal_width() -> usize {
+    parse_columns(std::env::var("COLUMNS").ok().as_deref())
+}
+
+/// The column count a `COLUMNS` value names, or 80 if it is unset, zero or
+/// not a number
+fn parse_columns(columns: Option<&str>) -> usize {
+    columns
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
+
+/// The width lines are padded out to, besides the widest line: the
+/// terminal's, asked of `columns` only when `to_terminal` is set, or `width`
+fn target_width(opts: &AlignOptions, columns: impl FnOnce() -> usize) -> Option<usize> {
+    if opts.to_terminal { Some(columns()) } else { opts.width }
+}
+
+/// Errors produced by `try_ansi_align`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlignError {
+    /// A line that needs padding contains a cursor-movement sequence
+    CursorMovement {
+        /// Index of the offending line
+        line: usize,
+    },
+}
+
+impl std::fmt::Display for AlignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlignError::CursorMovement { line } => write!(
+                f,
+                "line {} contains a cursor-movement sequence and cannot be padded",
+                line
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AlignError {}
+
+/// Check whether a line contains a CSI cursor-movement sequence
+/// (e.g. `\u{001B}[3C`), whose visual result would shift if padding
+/// were prepended to the line.
+fn has_cursor_movement(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == 0x1B && bytes[i + 1] == b'[' {
+            let mut j = i + 2;
+            while j < bytes.len() && (0x20..=0x3F).contains(&bytes[j]) {
+                j += 1;
+            }
+            if j < bytes.len() && matches!(bytes[j], b'A'..=b'H' | b'f' | b'd') {
+                return true;
+            }
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+    false
+}
+
+/// The byte length of the run of escape sequences at the start of `line`
+fn leading_escapes_len(line: &str) -> usize {
+    let mut len = 0;
+    while line[len..].starts_with('\u{001B}') {
+        len += ansi_sequence_len(&line[len..]);
+    }
+    len
 }
 
 /// Align text with support for ANSI escape sequences
 /// 
+/// Lines containing cursor-movement sequences are left unpadded; see
+/// `try_ansi_align` to treat them as an error instead.
+/// 
 /// # Examples
 /// 
 /// ```
@@ -69,47 +334,345 @@ impl AlignOptions {
 /// let result = ansi_align("hello\nworld", Some(opts));
 /// ```
 pub fn ansi_align(text: &str, opts: Option<AlignOptions>) -> String {
-    if text.is_empty() {
-        return text.to_string();
-    }
+    let opts = opts.unwrap_or_default();
+    align_text(text, &opts, false, &mut string_width).map_or_else(|_| text.to_string(), Cow::into_owned)
+}
 
+/// Align text like `ansi_align`, borrowing the input when nothing changes
+///
+/// Text whose lines already share one width (or that is left-aligned) needs
+/// no padding, so it comes back as `Cow::Borrowed` without being rebuilt.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use ansi_align::ansi_align_cow;
+///
+/// assert!(matches!(ansi_align_cow("ab\ncd", None), Cow::Borrowed("ab\ncd")));
+/// assert_eq!(ansi_align_cow("a\nabc", None), " a\nabc");
+/// ```
+pub fn ansi_align_cow(text: &str, opts: Option<AlignOptions>) -> Cow<'_, str> {
     let opts = opts.unwrap_or_default();
-    
-    // Short-circuit left alignment as no-op
-    if opts.align == Alignment::Left {
-        return text.to_string();
-    }
+    align_text(text, &opts, false, &mut string_width).unwrap_or(Cow::Borrowed(text))
+}
+
+/// Align text like `ansi_align`, measuring lines with a custom strategy
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{ansi_align_with, AlignOptions, Alignment};
+/// use string_width::WidthStrategy;
+///
+/// // Every byte is one column
+/// struct Bytes;
+///
+/// impl WidthStrategy for Bytes {
+///     fn width(&self, s: &str) -> usize {
+///         s.len()
+///     }
+/// }
+///
+/// let opts = AlignOptions::new(Alignment::Right);
+/// assert_eq!(ansi_align_with("古\nabcd", Some(opts), &Bytes), " 古\nabcd");
+/// ```
+pub fn ansi_align_with<W: WidthStrategy + ?Sized>(
+    text: &str,
+    opts: Option<AlignOptions>,
+    strategy: &W,
+) -> String {
+    let opts = opts.unwrap_or_default();
+    align_text(text, &opts, false, &mut |line| strategy.width(line))
+        .map_or_else(|_| text.to_string(), Cow::into_owned)
+}
+
+/// Align text like `ansi_align`, measuring lines through a shared cache
+///
+/// Re-aligning unchanged content (e.g. redrawing a box every frame) then
+/// skips measuring lines the cache has already seen.
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{ansi_align, ansi_align_cached};
+/// use string_width::WidthCache;
+///
+/// let mut cache = WidthCache::new();
+/// let first = ansi_align_cached("a\nabc", None, &mut cache);
+/// let second = ansi_align_cached("a\nabc", None, &mut cache);
+/// assert_eq!(first, ansi_align("a\nabc", None));
+/// assert_eq!(first, second);
+/// assert_eq!(cache.hits(), 2);
+/// ```
+pub fn ansi_align_cached(text: &str, opts: Option<AlignOptions>, cache: &mut WidthCache) -> String {
+    let opts = opts.unwrap_or_default();
+    align_text(text, &opts, false, &mut |line| cache.width(line))
+        .map_or_else(|_| text.to_string(), Cow::into_owned)
+}
 
-    let lines: Vec<&str> = text.split(&opts.split).collect();
-    
-    // Calculate width for each line and find maximum
-    let line_data: Vec<(String, usize)> = lines
+/// Align several independent blocks with the same options
+///
+/// Each block is aligned against its own widest line (or `width`), exactly
+/// as separate `ansi_align` calls would, without cloning the options per call.
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{ansi_align_all, AlignOptions, Alignment};
+///
+/// let opts = AlignOptions::new(Alignment::Right);
+/// assert_eq!(ansi_align_all(&["a\nbb", "ccc\nd"], &opts), vec![" a\nbb", "ccc\n  d"]);
+/// ```
+pub fn ansi_align_all(blocks: &[&str], opts: &AlignOptions) -> Vec<String> {
+    blocks
         .iter()
-        .map(|line| {
-            let line_str = line.to_string();
-            let width = string_width(&line_str);
-            (line_str, width)
-        })
+        .map(|block| align_text(block, opts, false, &mut string_width).map_or_else(|_| block.to_string(), Cow::into_owned))
+        .collect()
+}
+
+/// Align text like `ansi_align`, but report lines that can't be padded safely
+///
+/// Lines containing cursor-movement sequences are never padded, since
+/// prepending spaces would shift where the cursor lands. When
+/// `strict_cursor` is set, such a line needing padding is an error instead.
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{try_ansi_align, Alignment, AlignOptions, AlignError};
+///
+/// let opts = AlignOptions::new(Alignment::Right).strict_cursor(true);
+/// let result = try_ansi_align("hi\n\u{001B}[3Cx\nhello", Some(opts));
+/// assert_eq!(result, Err(AlignError::CursorMovement { line: 1 }));
+/// ```
+pub fn try_ansi_align(text: &str, opts: Option<AlignOptions>) -> Result<String, AlignError> {
+    let opts = opts.unwrap_or_default();
+    align_text(text, &opts, opts.strict_cursor, &mut string_width).map(Cow::into_owned)
+}
+
+/// Align text choosing each line's alignment with a closure
+///
+/// `f` receives every line's index and content and returns its alignment,
+/// in place of `opts.align`; all lines still share the width of the widest
+/// one. Other options apply as in `ansi_align`.
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{ansi_align_by, AlignOptions, Alignment};
+///
+/// let heading_centered = |index: usize, _: &str| match index {
+///     0 => Alignment::Center,
+///     _ => Alignment::Left,
+/// };
+/// let result = ansi_align_by("Title\nfirst line\nsecond", heading_centered, &AlignOptions::default());
+/// assert_eq!(result, "  Title\nfirst line\nsecond");
+/// ```
+pub fn ansi_align_by<F: Fn(usize, &str) -> Alignment>(text: &str, f: F, opts: &AlignOptions) -> String {
+    align_text_by(text, opts, &f, false, &mut string_width, &terminal_width).map_or_else(|_| text.to_string(), Cow::into_owned)
+}
+
+/// Compute per-line padding without building the aligned string
+///
+/// Returns a `(left_pad, right_pad, line)` tuple for every line, where the
+/// pads are counted in columns and together bring each line up to the shared
+/// width. `ansi_align` only emits `right_pad` when `pad_both` is set; callers
+/// laying out styled spans can apply either side however they like.
+///
+/// In justify mode the gap of every line but the last is reported as
+/// `right_pad`, since justification spends it between words rather than at
+/// the edges.
+///
+/// # Examples
+///
+/// ```
+/// use ansi_align::{compute_alignment, AlignOptions, Alignment};
+///
+/// let opts = AlignOptions::new(Alignment::Right);
+/// assert_eq!(compute_alignment("a\nabc", &opts), vec![(2, 0, "a"), (0, 0, "abc")]);
+/// ```
+pub fn compute_alignment<'a>(text: &'a str, opts: &AlignOptions) -> Vec<(usize, usize, &'a str)> {
+    place_lines(text, opts, &mut string_width)
+}
+
+fn place_lines<'a>(
+    text: &'a str,
+    opts: &AlignOptions,
+    measure: &mut dyn FnMut(&str) -> usize,
+) -> Vec<(usize, usize, &'a str)> {
+    place_lines_by(text, opts, &|_, _| opts.align, measure, &terminal_width)
+}
+
+/// Place lines like `place_lines`, with `align_for` choosing each line's
+/// alignment from its index and content in place of `opts.align`, and
+/// `columns` giving the terminal width for `to_terminal`
+fn place_lines_by<'a>(
+    text: &'a str,
+    opts: &AlignOptions,
+    align_for: &dyn Fn(usize, &str) -> Alignment,
+    measure: &mut dyn FnMut(&str) -> usize,
+    columns: &dyn Fn() -> usize,
+) -> Vec<(usize, usize, &'a str)> {
+    let line_data: Vec<(&str, usize)> = text
+        .split(opts.line_separator())
+        .map(|line| (line, measure(line)))
         .collect();
-    
-    let max_width = line_data.iter().map(|(_, width)| *width).max().unwrap_or(0);
-    
-    // Apply alignment to each line
-    let aligned_lines: Vec<String> = line_data
+
+    let widest = line_data.iter().map(|(_, width)| *width).max().unwrap_or(0);
+    let target = target_width(opts, columns);
+    let max_width = widest.max(target.unwrap_or(0));
+
+    let last = line_data.len() - 1;
+    line_data
         .into_iter()
-        .map(|(line_str, width)| {
-            let padding_needed = match opts.align {
-                Alignment::Left => 0, // Already handled above
-                Alignment::Center => (max_width - width) / 2,
-                Alignment::Right => max_width - width,
+        .enumerate()
+        .map(|(index, (line, width))| {
+            let gap = max_width - width;
+            let align = match (opts.justify, index == last) {
+                (false, _) => align_for(index, line),
+                (true, false) => Alignment::Left,
+                (true, true) => opts.last_line_align.unwrap_or(Alignment::Left),
+            }
+            .resolve(line);
+            let left_pad = match (align, opts.center_bias) {
+                (Alignment::Left, _) => 0,
+                (Alignment::Center, CenterBias::Left) => gap / 2,
+                (Alignment::Center, CenterBias::Right) => gap - gap / 2,
+                (Alignment::Right, _) => gap,
+                (Alignment::Smart, _) => unreachable!("resolved above"),
             };
-            
-            let padding: String = opts.pad.to_string().repeat(padding_needed);
-            format!("{}{}", padding, line_str)
+            (left_pad, gap - left_pad, line)
         })
-        .collect();
-    
-    aligned_lines.join(&opts.split)
+        .collect()
+}
+
+/// Stretch a line by `extra` columns, spread over the spaces between words
+/// with the leftmost gaps taking any remainder. Lines without a space are
+/// returned unchanged.
+fn justify_line(line: &str, extra: usize) -> Option<String> {
+    let gaps = line.matches(' ').count();
+    if gaps == 0 {
+        return None;
+    }
+
+    let mut justified = String::with_capacity(line.len() + extra);
+    for (index, word) in line.split(' ').enumerate() {
+        if index > 0 {
+            let widen = extra / gaps + usize::from(index <= extra % gaps);
+            justified.push(' ');
+            justified.push_str(&" ".repeat(widen));
+        }
+        justified.push_str(word);
+    }
+    Some(justified)
+}
+
+fn align_text<'a>(
+    text: &'a str,
+    opts: &AlignOptions,
+    strict_cursor: bool,
+    measure: &mut dyn FnMut(&str) -> usize,
+) -> Result<Cow<'a, str>, AlignError> {
+    // Short-circuit left alignment as no-op
+    if opts.align == Alignment::Left && !opts.pad_both && !opts.justify {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    align_text_by(text, opts, &|_, _| opts.align, strict_cursor, measure, &terminal_width)
+}
+
+fn align_text_by<'a>(
+    text: &'a str,
+    opts: &AlignOptions,
+    align_for: &dyn Fn(usize, &str) -> Alignment,
+    strict_cursor: bool,
+    measure: &mut dyn FnMut(&str) -> usize,
+    columns: &dyn Fn() -> usize,
+) -> Result<Cow<'a, str>, AlignError> {
+    if text.is_empty() {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let placements = place_lines_by(text, opts, align_for, measure, columns);
+    // Right-hand gaps are only ever filled when padding both sides or
+    // justifying, so otherwise a line with no left gap is left as is
+    let fills_right = opts.pad_both || opts.justify;
+    if placements
+        .iter()
+        .all(|&(left, right, _)| left == 0 && (right == 0 || !fills_right))
+    {
+        return Ok(Cow::Borrowed(text));
+    }
+    let last = placements.len() - 1;
+    // Lines are written straight into one buffer, so the only per-line
+    // allocations left are for justified lines and `pad_str` runs
+    let mut out = String::with_capacity(text.len() + placements.len() * 8);
+    let mut pad = |out: &mut String, width: usize, clipped_first: bool| match opts.pad_str.as_deref() {
+        Some(unit) => out.push_str(&pad_run(unit, width, clipped_first, measure)),
+        None => out.extend(std::iter::repeat_n(opts.pad, width)),
+    };
+    for (index, (padding_needed, trailing_needed, line)) in placements.into_iter().enumerate() {
+        if index > 0 {
+            out.push_str(opts.line_separator());
+        }
+        if opts.justify && index < last && trailing_needed > 0 {
+            if has_cursor_movement(line) {
+                if strict_cursor {
+                    return Err(AlignError::CursorMovement { line: index });
+                }
+            } else if let Some(justified) = justify_line(line, trailing_needed) {
+                out.push_str(&justified);
+                continue;
+            }
+        }
+
+        let trailing_needed = if opts.pad_both { trailing_needed } else { 0 };
+
+        if padding_needed > 0 && has_cursor_movement(line) {
+            if strict_cursor {
+                return Err(AlignError::CursorMovement { line: index });
+            }
+            out.push_str(line);
+            continue;
+        }
+
+        let (prefix, rest) = if opts.pad_before_ansi {
+            ("", line)
+        } else {
+            line.split_at(leading_escapes_len(line))
+        };
+        out.push_str(prefix);
+        pad(&mut out, padding_needed, false);
+        out.push_str(rest);
+        pad(&mut out, trailing_needed, true);
+    }
+
+    Ok(Cow::Owned(out))
+}
+
+/// Repeat `unit` to fill exactly `width` columns.
+///
+/// Whole units are laid end to end and the leftover columns take a clipped
+/// copy of the unit, placed first when `clipped_first` so it sits against
+/// content on its left. Anything the clip can't fill, such as half of a
+/// wide character, becomes spaces.
+fn pad_run(unit: &str, width: usize, clipped_first: bool, measure: &mut dyn FnMut(&str) -> usize) -> String {
+    let unit_width = measure(unit);
+    if unit_width == 0 {
+        return " ".repeat(width);
+    }
+
+    let whole = unit.repeat(width / unit_width);
+    let rest = width % unit_width;
+    let mut clipped = truncate_ansi(unit, rest);
+    let clipped_width = measure(&clipped);
+    clipped.push_str(&" ".repeat(rest.saturating_sub(clipped_width)));
+    if clipped_first {
+        clipped + &whole
+    } else {
+        whole + &clipped
+    }
 }
 
 /// Align text to the left (no-op, returns original text)
@@ -184,6 +747,14 @@ mod tests {
         assert_eq!(result, ".a|bb");
     }
 
+    #[test]
+    fn test_empty_split_falls_back_to_newline() {
+        let opts = AlignOptions::new(Alignment::Right).split("");
+        assert_eq!(ansi_align("a\nabc", Some(opts.clone())), "  a\nabc");
+        assert_eq!(ansi_align("abc", Some(opts.clone())), "abc");
+        assert_eq!(compute_alignment("a\nabc", &opts), vec![(2, 0, "a"), (0, 0, "abc")]);
+    }
+
     #[test]
     fn test_empty_string() {
         assert_eq!(ansi_align("", None), "");
@@ -199,4 +770,305 @@ mod tests {
         assert_eq!(center(text), "hello");
         assert_eq!(right(text), "hello");
     }
+
+    #[test]
+    fn test_cursor_movement_left_unpadded() {
+        let text = "hi\n\u{001B}[3Cx\nhello";
+        let result = right(text);
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "   hi");
+        assert_eq!(lines[1], "\u{001B}[3Cx"); // Cursor-forward line untouched
+        assert_eq!(lines[2], "hello");
+    }
+
+    #[test]
+    fn test_cursor_movement_strict_error() {
+        let text = "hi\n\u{001B}[3Cx\nhello";
+        let opts = AlignOptions::new(Alignment::Center).strict_cursor(true);
+        let result = try_ansi_align(text, Some(opts));
+        assert_eq!(result, Err(AlignError::CursorMovement { line: 1 }));
+
+        // Without strict mode the line is simply left alone
+        let opts = AlignOptions::new(Alignment::Center);
+        assert_eq!(try_ansi_align(text, Some(opts)), Ok(center(text)));
+    }
+
+    #[test]
+    fn test_non_cursor_sequences_still_padded() {
+        // SGR and private-mode sequences are not cursor movement
+        assert!(!has_cursor_movement("\u{001B}[1mbold\u{001B}[0m"));
+        assert!(!has_cursor_movement("\u{001B}[?25l"));
+        assert!(has_cursor_movement("\u{001B}[2;5H"));
+
+        let opts = AlignOptions::new(Alignment::Right).strict_cursor(true);
+        let result = try_ansi_align("\u{001B}[1mhi\u{001B}[0m\nhello", Some(opts));
+        assert_eq!(result, Ok("   \u{001B}[1mhi\u{001B}[0m\nhello".to_string()));
+    }
+
+    #[test]
+    fn test_pad_both_center_bias() {
+        let opts = AlignOptions::new(Alignment::Center)
+            .pad('.')
+            .pad_both(true)
+            .width(6);
+        assert_eq!(ansi_align("x", Some(opts.clone())), "..x...");
+
+        let opts = opts.center_bias(CenterBias::Right);
+        assert_eq!(ansi_align("x", Some(opts)), "...x..");
+    }
+
+    #[test]
+    fn test_pad_both_fills_every_line() {
+        let opts = AlignOptions::new(Alignment::Center).pad('.').pad_both(true);
+        assert_eq!(ansi_align("hi\nhello\n古", Some(opts)), ".hi..\nhello\n.古..");
+
+        let opts = AlignOptions::new(Alignment::Left).pad('.').pad_both(true);
+        assert_eq!(ansi_align("hi\nhello", Some(opts)), "hi...\nhello");
+
+        let opts = AlignOptions::new(Alignment::Right).pad('.').pad_both(true);
+        assert_eq!(ansi_align("hi\nhello", Some(opts)), "...hi\nhello");
+    }
+
+    #[test]
+    fn test_width_option() {
+        let opts = AlignOptions::new(Alignment::Right).width(5);
+        assert_eq!(ansi_align("hi", Some(opts)), "   hi");
+
+        // A width narrower than the widest line has no effect
+        let opts = AlignOptions::new(Alignment::Right).width(2);
+        assert_eq!(ansi_align("hi\nhello", Some(opts)), "   hi\nhello");
+    }
+
+    #[test]
+    fn test_compute_alignment_center() {
+        let opts = AlignOptions::new(Alignment::Center);
+        let placements = compute_alignment("hi\nhello", &opts);
+        assert_eq!(placements, vec![(1, 2, "hi"), (0, 0, "hello")]);
+
+        let opts = opts.center_bias(CenterBias::Right);
+        let placements = compute_alignment("hi\nhello", &opts);
+        assert_eq!(placements, vec![(2, 1, "hi"), (0, 0, "hello")]);
+    }
+
+    #[test]
+    fn test_justify_last_line_align() {
+        let text = "the quick brown\nfox jumps\nover";
+
+        let opts = AlignOptions::new(Alignment::Left).justify(true);
+        assert_eq!(ansi_align(text, Some(opts)), "the quick brown\nfox       jumps\nover");
+
+        let opts = AlignOptions::new(Alignment::Left)
+            .justify(true)
+            .last_line_align(Alignment::Center)
+            .pad_both(true);
+        assert_eq!(
+            ansi_align(text, Some(opts)),
+            "the quick brown\nfox       jumps\n     over      "
+        );
+    }
+
+    #[test]
+    fn test_justify_spreads_remainder_left() {
+        let opts = AlignOptions::new(Alignment::Left).justify(true);
+        assert_eq!(ansi_align("a b c\nabcdefgh", Some(opts)), "a   b  c\nabcdefgh");
+    }
+
+    #[test]
+    fn test_alignment_from_str() {
+        assert_eq!("left".parse(), Ok(Alignment::Left));
+        assert_eq!("center".parse(), Ok(Alignment::Center));
+        assert_eq!("right".parse(), Ok(Alignment::Right));
+    }
+
+    #[test]
+    fn test_alignment_from_str_ignores_case() {
+        assert_eq!("LEFT".parse(), Ok(Alignment::Left));
+        assert_eq!("Center".parse(), Ok(Alignment::Center));
+        assert_eq!("rIGHT".parse(), Ok(Alignment::Right));
+    }
+
+    #[test]
+    fn test_alignment_from_str_invalid() {
+        let err = "middle".parse::<Alignment>().unwrap_err();
+        assert_eq!(err, ParseAlignmentError("middle".to_string()));
+        assert_eq!(
+            err.to_string(),
+            "unknown alignment `middle`, expected left, center, right or smart"
+        );
+    }
+
+    #[test]
+    fn test_ansi_align_cached_reuses_measurements() {
+        let mut cache = WidthCache::new();
+        let text = "hello\n古古古古\nhi";
+        let opts = AlignOptions::new(Alignment::Right);
+
+        let first = ansi_align_cached(text, Some(opts.clone()), &mut cache);
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.len(), 3);
+
+        let second = ansi_align_cached(text, Some(opts.clone()), &mut cache);
+        assert_eq!(cache.hits(), 3);
+        assert_eq!(first, second);
+        assert_eq!(first, ansi_align(text, Some(opts)));
+    }
+
+    #[test]
+    fn test_ansi_align_with_mock_strategy() {
+        struct DoubleBytes;
+
+        impl WidthStrategy for DoubleBytes {
+            fn width(&self, s: &str) -> usize {
+                s.len() * 2
+            }
+        }
+
+        // "ab" measures 4 and "abcd" 8, so "ab" needs 4 pad columns
+        let opts = AlignOptions::new(Alignment::Right);
+        assert_eq!(ansi_align_with("ab\nabcd", Some(opts.clone()), &DoubleBytes), "    ab\nabcd");
+        assert_eq!(ansi_align_with("ab\nabcd", Some(opts.clone()), &string_width::UnicodeWidth), ansi_align("ab\nabcd", Some(opts)));
+    }
+
+    #[test]
+    fn test_ansi_align_with_fixed_table() {
+        let table = string_width::FixedTable(std::collections::HashMap::from([('i', 0), ('W', 2)]));
+
+        // "iii" measures 0 and "WW" 4, so "iii" gets all four pad columns
+        let opts = AlignOptions::new(Alignment::Right);
+        assert_eq!(ansi_align_with("iii\nWW", Some(opts), &table), "    iii\nWW");
+
+        // Unlisted chars advance by one
+        let opts = AlignOptions::new(Alignment::Center);
+        assert_eq!(ansi_align_with("ab\nWWi", Some(opts), &table), " ab\nWWi");
+    }
+
+    #[test]
+    fn test_ansi_align_cow_borrows_aligned_text() {
+        let text = "\u{001B}[31mabc\u{001B}[0m\nxyz\n古a";
+        for align in [Alignment::Left, Alignment::Center, Alignment::Right] {
+            let opts = AlignOptions::new(align).pad_both(true);
+            let result = ansi_align_cow(text, Some(opts));
+            assert!(matches!(result, Cow::Borrowed(borrowed) if std::ptr::eq(borrowed, text)));
+        }
+
+        // A line that needs padding is rebuilt
+        let result = ansi_align_cow("ab\nabcd", Some(AlignOptions::new(Alignment::Right)));
+        assert!(matches!(result, Cow::Owned(_)));
+        assert_eq!(result, "  ab\nabcd");
+
+        // Trailing gaps only force a rebuild when they would be filled
+        let opts = AlignOptions::new(Alignment::Left).pad_both(true);
+        assert_eq!(ansi_align_cow("ab\nabcd", Some(opts)), "ab  \nabcd");
+    }
+
+    #[test]
+    fn test_to_terminal_uses_columns() {
+        let opts = AlignOptions::new(Alignment::Center).width(10);
+        assert_eq!(target_width(&opts, || 20), Some(10));
+        assert_eq!(target_width(&opts.to_terminal(true), || 20), Some(20));
+
+        // Centering against a 20-column terminal, end to end
+        let opts = AlignOptions::new(Alignment::Center).to_terminal(true);
+        let centered = align_text_by("hi", &opts, &|_, _| opts.align, false, &mut string_width, &|| 20);
+        assert_eq!(centered.unwrap(), "         hi");
+
+        assert_eq!(parse_columns(Some(" 20 ")), 20);
+        for unusable in [None, Some("0"), Some("wide"), Some("")] {
+            assert_eq!(parse_columns(unusable), 80);
+        }
+    }
+
+    #[test]
+    fn test_ansi_only_line_measures_zero() {
+        let text = "hello\n\u{001B}[0m\nhi";
+
+        let opts = AlignOptions::new(Alignment::Right);
+        assert_eq!(ansi_align(text, Some(opts)), "hello\n     \u{001B}[0m\n   hi");
+
+        // In block mode the escape-only line is padded out to the full width
+        let opts = AlignOptions::new(Alignment::Center).pad_both(true);
+        assert_eq!(
+            ansi_align(text, Some(opts)),
+            "hello\n  \u{001B}[0m   \n hi  "
+        );
+    }
+
+    #[test]
+    fn test_ansi_align_all_uses_each_blocks_width() {
+        let opts = AlignOptions::new(Alignment::Center);
+        let blocks = ["a\nabc", "x\nwxyz\nvwxyz", "\u{001B}[1m古\u{001B}[22m\n古古古"];
+        let aligned = ansi_align_all(&blocks, &opts);
+
+        assert_eq!(aligned[0], " a\nabc");
+        assert_eq!(aligned[1], "  x\nwxyz\nvwxyz");
+        assert_eq!(aligned[2], "  \u{001B}[1m古\u{001B}[22m\n古古古");
+        for (block, result) in blocks.iter().zip(&aligned) {
+            assert_eq!(*result, ansi_align(block, Some(opts.clone())));
+        }
+    }
+
+    #[test]
+    fn test_pad_before_ansi() {
+        let text = "\u{001B}[41m\u{001B}[1mhi\u{001B}[0m\nhello";
+
+        let opts = AlignOptions::new(Alignment::Right);
+        assert_eq!(
+            ansi_align(text, Some(opts.clone())),
+            "   \u{001B}[41m\u{001B}[1mhi\u{001B}[0m\nhello"
+        );
+
+        let opts = opts.pad_before_ansi(false);
+        assert_eq!(
+            ansi_align(text, Some(opts)),
+            "\u{001B}[41m\u{001B}[1m   hi\u{001B}[0m\nhello"
+        );
+    }
+
+    #[test]
+    fn test_pad_str_clips_last_unit() {
+        let text = "a\nabcd";
+
+        let opts = AlignOptions::new(Alignment::Right).pad_str("-=");
+        assert_eq!(ansi_align(text, Some(opts)), "-=-a\nabcd");
+
+        // Half a wide character can't be drawn, so the odd column is a space
+        let opts = AlignOptions::new(Alignment::Right).pad_str("古");
+        let result = ansi_align(text, Some(opts));
+        assert_eq!(result, "古 a\nabcd");
+        assert!(result.split('\n').all(|line| string_width(line) == 4));
+
+        // Trailing padding clips the unit touching the content
+        let opts = AlignOptions::new(Alignment::Center).pad_str("古").pad_both(true).width(6);
+        assert_eq!(ansi_align("a", Some(opts)), "古a 古");
+    }
+
+    #[test]
+    fn test_smart_alignment() {
+        let opts = AlignOptions::new(Alignment::Smart);
+        assert_eq!(
+            ansi_align("total\n42\n\u{001B}[1m-3.5\u{001B}[22m\nNaN", Some(opts)),
+            "total\n   42\n \u{001B}[1m-3.5\u{001B}[22m\nNaN"
+        );
+        assert_eq!("Smart".parse::<Alignment>(), Ok(Alignment::Smart));
+    }
+
+    #[test]
+    fn test_ansi_align_by_index() {
+        let text = "Heading\nab\nabcdefghi";
+        let result = ansi_align_by(
+            text,
+            |index, _| if index == 0 { Alignment::Center } else { Alignment::Left },
+            &AlignOptions::default(),
+        );
+        assert_eq!(result, " Heading\nab\nabcdefghi");
+
+        // The closure sees each line's content too
+        let opts = AlignOptions::default().pad('.');
+        let result = ansi_align_by(
+            "x\nyy\nzzz",
+            |_, line| if line.starts_with('y') { Alignment::Right } else { Alignment::Left },
+            &opts,
+        );
+        assert_eq!(result, "x\n.yy\nzzz");
+    }
 }
@@ -1,7 +1,81 @@
 use string_width::{string_width, widest_line};
 use ansi_align::{left, center, right, ansi_align, Alignment, AlignOptions};
+use boxen::{boxen, BoxenOptions, BorderStyle, Spacing};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: boxen-rs-bin [--file=PATH]... [-]
+
+With no arguments, prints a demo of every crate in the workspace.
+  --file=PATH  box the contents of PATH; a directory boxes each file in it
+  -            box standard input";
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        demo();
+        return;
+    }
+
+    match box_inputs(&args) {
+        Ok(output) => println!("{}", output),
+        Err(message) => {
+            eprintln!("boxen-rs-bin: {}\n\n{}", message, USAGE);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Box every input named on the command line, one box per file, separated
+/// by blank lines
+fn box_inputs(args: &[String]) -> Result<String, String> {
+    let mut contents = Vec::new();
+    for arg in args {
+        if arg == "-" {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|err| format!("cannot read standard input: {}", err))?;
+            contents.push(text);
+        } else if let Some(path) = arg.strip_prefix("--file=") {
+            for file in input_files(Path::new(path))? {
+                let text = std::fs::read_to_string(&file)
+                    .map_err(|err| format!("cannot read {}: {}", file.display(), err))?;
+                contents.push(text);
+            }
+        } else {
+            return Err(format!("unknown argument `{}`", arg));
+        }
+    }
+
+    let boxes: Vec<String> = contents
+        .iter()
+        .map(|text| boxen(text.strip_suffix('\n').unwrap_or(text), None))
+        .collect();
+    Ok(boxes.join("\n\n"))
+}
+
+/// The files to box for `path`: the path itself, or the files directly
+/// inside it in name order when it is a directory
+fn input_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let entries = std::fs::read_dir(path)
+        .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        if entry.path().is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn demo() {
     println!("String Width Demo");
     println!("=================");
 
@@ -90,4 +164,25 @@ fn main() {
         
         println!();
     }
+    
+    println!("\nBoxen Demo");
+    println!("==========");
+    
+    let box_tests = vec![
+        (BorderStyle::Single, "Single border"),
+        (BorderStyle::Double, "Double border"),
+        (BorderStyle::Round, "Round border"),
+        (BorderStyle::Bold, "Bold border"),
+        (BorderStyle::Classic, "Classic border"),
+    ];
+    
+    for (style, description) in box_tests {
+        println!("{}", description);
+        let opts = BoxenOptions::new()
+            .border_style(style)
+            .padding(Spacing { top: 0, right: 1, bottom: 0, left: 1 })
+            .separators(vec![0]);
+        println!("{}", boxen("Header\nhello\n古古古", Some(opts)));
+        println!();
+    }
 }
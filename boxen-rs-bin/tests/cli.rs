@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A scratch directory unique to this test run
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("boxen-rs-bin-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> (bool, String) {
+    // The binary reads its defaults from the environment, so it runs
+    // without the shell's settings for them
+    let output = Command::new(env!("CARGO_BIN_EXE_boxen-rs-bin"))
+        .args(args)
+        .env_remove("BOXEN_STYLE")
+        .env_remove("COLUMNS")
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_file_is_boxed() {
+    let dir = scratch_dir("single");
+    let file = dir.join("note.txt");
+    std::fs::write(&file, "hello\n古古\n").unwrap();
+
+    let (ok, stdout) = run(&[&format!("--file={}", file.display())]);
+    assert!(ok);
+    assert_eq!(stdout, "┌─────┐\n│hello│\n│古古 │\n└─────┘\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_two_files_give_two_boxes() {
+    let dir = scratch_dir("pair");
+    std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+    std::fs::write(dir.join("b.txt"), "bb\n").unwrap();
+
+    let expected = "┌─┐\n│a│\n└─┘\n\n┌──┐\n│bb│\n└──┘\n";
+    let (ok, stdout) = run(&[
+        &format!("--file={}", dir.join("a.txt").display()),
+        &format!("--file={}", dir.join("b.txt").display()),
+    ]);
+    assert!(ok);
+    assert_eq!(stdout, expected);
+
+    // A directory boxes each file inside it, in name order
+    let (ok, stdout) = run(&[&format!("--file={}", dir.display())]);
+    assert!(ok);
+    assert_eq!(stdout, expected);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_missing_file_fails() {
+    let (ok, stdout) = run(&["--file=/nonexistent/boxen-rs-bin-input"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+}
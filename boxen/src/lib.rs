@@ -0,0 +1,4961 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+
+use ansi_align::Alignment;
+use string_width::{
+    UnicodeWidth, WidthStrategy, ansi_sequence_len, expand_tabs, is_line_break, string_width,
+    truncate_ansi,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The set of glyphs used to draw a box border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorderChars {
+    pub top_left: &'static str,
+    pub top_right: &'static str,
+    pub bottom_left: &'static str,
+    pub bottom_right: &'static str,
+    pub horizontal: &'static str,
+    pub vertical: &'static str,
+    /// Junction where a separator row meets the left edge (e.g. `├`)
+    pub left_tee: &'static str,
+    /// Junction where a separator row meets the right edge (e.g. `┤`)
+    pub right_tee: &'static str,
+    /// Junction where a grid line meets the top edge (e.g. `┬`)
+    pub top_tee: &'static str,
+    /// Junction where a grid line meets the bottom edge (e.g. `┴`)
+    pub bottom_tee: &'static str,
+    /// Crossing of a grid line and a separator row (e.g. `┼`)
+    pub cross: &'static str,
+}
+
+/// Border styles available for drawing boxes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderStyle {
+    #[default]
+    Single,
+    Double,
+    Round,
+    Bold,
+    SingleDouble,
+    DoubleSingle,
+    /// The `+-|` style from JS boxen
+    Classic,
+    /// Plain 7-bit ASCII glyphs only, safe for any terminal, font or pipe
+    /// that mangles Unicode
+    Ascii,
+    Custom(BorderChars),
+}
+
+impl BorderStyle {
+    /// All built-in styles, excluding `Custom`
+    const PRESETS: [BorderStyle; 8] = [
+        BorderStyle::Single,
+        BorderStyle::Double,
+        BorderStyle::Round,
+        BorderStyle::Bold,
+        BorderStyle::SingleDouble,
+        BorderStyle::DoubleSingle,
+        BorderStyle::Classic,
+        BorderStyle::Ascii,
+    ];
+
+    /// The resolved glyph set for this style
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::BorderStyle;
+    ///
+    /// assert_eq!(BorderStyle::Round.chars().top_left, "╭");
+    /// assert_eq!(BorderStyle::Double.chars().horizontal, "═");
+    /// ```
+    pub fn chars(&self) -> BorderChars {
+        match self {
+            BorderStyle::Single => BorderChars {
+                top_left: "┌",
+                top_right: "┐",
+                bottom_left: "└",
+                bottom_right: "┘",
+                horizontal: "─",
+                vertical: "│",
+                left_tee: "├",
+                right_tee: "┤",
+                top_tee: "┬",
+                bottom_tee: "┴",
+                cross: "┼",
+            },
+            BorderStyle::Double => BorderChars {
+                top_left: "╔",
+                top_right: "╗",
+                bottom_left: "╚",
+                bottom_right: "╝",
+                horizontal: "═",
+                vertical: "║",
+                left_tee: "╠",
+                right_tee: "╣",
+                top_tee: "╦",
+                bottom_tee: "╩",
+                cross: "╬",
+            },
+            BorderStyle::Round => BorderChars {
+                top_left: "╭",
+                top_right: "╮",
+                bottom_left: "╰",
+                bottom_right: "╯",
+                horizontal: "─",
+                vertical: "│",
+                left_tee: "├",
+                right_tee: "┤",
+                top_tee: "┬",
+                bottom_tee: "┴",
+                cross: "┼",
+            },
+            BorderStyle::Bold => BorderChars {
+                top_left: "┏",
+                top_right: "┓",
+                bottom_left: "┗",
+                bottom_right: "┛",
+                horizontal: "━",
+                vertical: "┃",
+                left_tee: "┣",
+                right_tee: "┫",
+                top_tee: "┳",
+                bottom_tee: "┻",
+                cross: "╋",
+            },
+            BorderStyle::SingleDouble => BorderChars {
+                top_left: "╓",
+                top_right: "╖",
+                bottom_left: "╙",
+                bottom_right: "╜",
+                horizontal: "─",
+                vertical: "║",
+                left_tee: "╟",
+                right_tee: "╢",
+                top_tee: "╥",
+                bottom_tee: "╨",
+                cross: "╫",
+            },
+            BorderStyle::DoubleSingle => BorderChars {
+                top_left: "╒",
+                top_right: "╕",
+                bottom_left: "╘",
+                bottom_right: "╛",
+                horizontal: "═",
+                vertical: "│",
+                left_tee: "╞",
+                right_tee: "╡",
+                top_tee: "╤",
+                bottom_tee: "╧",
+                cross: "╪",
+            },
+            // Classic tracks JS boxen; Ascii promises 7-bit output and
+            // happens to share its glyphs today
+            BorderStyle::Classic | BorderStyle::Ascii => BorderChars {
+                top_left: "+",
+                top_right: "+",
+                bottom_left: "+",
+                bottom_right: "+",
+                horizontal: "-",
+                vertical: "|",
+                left_tee: "+",
+                right_tee: "+",
+                top_tee: "+",
+                bottom_tee: "+",
+                cross: "+",
+            },
+            BorderStyle::Custom(chars) => *chars,
+        }
+    }
+}
+
+/// The error returned when parsing an unknown border style name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBorderStyleError(String);
+
+impl std::fmt::Display for ParseBorderStyleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown border style `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseBorderStyleError {}
+
+impl std::str::FromStr for BorderStyle {
+    type Err = ParseBorderStyleError;
+
+    /// Parse a preset name such as `double` or `singleDouble`; case, dashes
+    /// and underscores are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "single" => Ok(BorderStyle::Single),
+            "double" => Ok(BorderStyle::Double),
+            "round" => Ok(BorderStyle::Round),
+            "bold" => Ok(BorderStyle::Bold),
+            "singledouble" => Ok(BorderStyle::SingleDouble),
+            "doublesingle" => Ok(BorderStyle::DoubleSingle),
+            "classic" => Ok(BorderStyle::Classic),
+            "ascii" => Ok(BorderStyle::Ascii),
+            _ => Err(ParseBorderStyleError(s.to_string())),
+        }
+    }
+}
+
+/// Spacing around the four sides of a box, in terminal cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Spacing {
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+    pub left: usize,
+}
+
+impl Spacing {
+    /// Spacing from a single number, the way JS boxen reads it: `n` rows
+    /// above and below, `3 * n` columns left and right, since a terminal
+    /// cell is roughly three times taller than it is wide.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::Spacing;
+    ///
+    /// let spacing = Spacing::scalar(1);
+    /// assert_eq!((spacing.top, spacing.left), (1, 3));
+    /// ```
+    pub fn scalar(n: usize) -> Self {
+        Self {
+            top: n,
+            right: n * 3,
+            bottom: n,
+            left: n * 3,
+        }
+    }
+
+    /// The same spacing `n` on all four sides, with no aspect correction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::Spacing;
+    ///
+    /// let spacing = Spacing::uniform(1);
+    /// assert_eq!((spacing.top, spacing.left), (1, 1));
+    /// ```
+    pub fn uniform(n: usize) -> Self {
+        Self {
+            top: n,
+            right: n,
+            bottom: n,
+            left: n,
+        }
+    }
+
+    /// `n` columns on the left and right, nothing above or below
+    pub fn horizontal(n: usize) -> Self {
+        Self::sym(n, 0)
+    }
+
+    /// `n` rows above and below, nothing on the left or right
+    pub fn vertical(n: usize) -> Self {
+        Self::sym(0, n)
+    }
+
+    /// `h` columns on the left and right and `v` rows above and below, taken
+    /// literally with no aspect correction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::Spacing;
+    ///
+    /// let spacing = Spacing::sym(2, 1);
+    /// assert_eq!((spacing.top, spacing.right, spacing.bottom, spacing.left), (1, 2, 1, 2));
+    /// ```
+    pub fn sym(h: usize, v: usize) -> Self {
+        Self {
+            top: v,
+            right: h,
+            bottom: v,
+            left: h,
+        }
+    }
+
+    /// The larger of each side, for resolving competing constraints
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::Spacing;
+    ///
+    /// let wider = Spacing::horizontal(3).max(Spacing::sym(1, 2));
+    /// assert_eq!(wider, Spacing::sym(3, 2));
+    /// ```
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            top: self.top.max(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+            left: self.left.max(other.left),
+        }
+    }
+
+    /// Each side less the matching side of `other`, stopping at 0
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self {
+            top: self.top.saturating_sub(other.top),
+            right: self.right.saturating_sub(other.right),
+            bottom: self.bottom.saturating_sub(other.bottom),
+            left: self.left.saturating_sub(other.left),
+        }
+    }
+}
+
+/// Side-by-side sums, e.g. padding plus margin for a box's total offset;
+/// sides saturate rather than overflow.
+impl std::ops::Add for Spacing {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            top: self.top.saturating_add(other.top),
+            right: self.right.saturating_add(other.right),
+            bottom: self.bottom.saturating_add(other.bottom),
+            left: self.left.saturating_add(other.left),
+        }
+    }
+}
+
+/// A bare number is read as `Spacing::scalar`, matching JS boxen.
+impl From<usize> for Spacing {
+    fn from(n: usize) -> Self {
+        Self::scalar(n)
+    }
+}
+
+/// A length across the terminal: a fixed number of cells, or a share of the
+/// terminal width resolved when the box is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// Exactly this many cells
+    Cells(usize),
+    /// This percentage of the terminal width, rounded down so the box never
+    /// takes more than its share
+    Percent(u8),
+}
+
+impl Dimension {
+    /// The number of cells this spans on a terminal `columns` wide
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::Dimension;
+    ///
+    /// assert_eq!(Dimension::Percent(50).resolve(80), 40);
+    /// assert_eq!(Dimension::Percent(33).resolve(80), 26);
+    /// assert_eq!(Dimension::Cells(12).resolve(80), 12);
+    /// ```
+    pub fn resolve(self, columns: usize) -> usize {
+        match self {
+            Dimension::Cells(cells) => cells,
+            Dimension::Percent(percent) => columns * usize::from(percent) / 100,
+        }
+    }
+}
+
+/// A bare number is a count of cells.
+impl From<usize> for Dimension {
+    fn from(cells: usize) -> Self {
+        Dimension::Cells(cells)
+    }
+}
+
+/// The sixteen standard terminal colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl NamedColor {
+    /// The SGR parameter selecting this color as the foreground (`30..=37`, `90..=97`)
+    pub fn fg_code(&self) -> u8 {
+        let (base, bright) = self.index();
+        if bright { 90 + base } else { 30 + base }
+    }
+
+    /// The SGR parameter selecting this color as the background (`40..=47`, `100..=107`)
+    pub fn bg_code(&self) -> u8 {
+        let (base, bright) = self.index();
+        if bright { 100 + base } else { 40 + base }
+    }
+
+    fn from_index(base: u8, bright: bool) -> NamedColor {
+        let colors = match bright {
+            false => [
+                NamedColor::Black,
+                NamedColor::Red,
+                NamedColor::Green,
+                NamedColor::Yellow,
+                NamedColor::Blue,
+                NamedColor::Magenta,
+                NamedColor::Cyan,
+                NamedColor::White,
+            ],
+            true => [
+                NamedColor::BrightBlack,
+                NamedColor::BrightRed,
+                NamedColor::BrightGreen,
+                NamedColor::BrightYellow,
+                NamedColor::BrightBlue,
+                NamedColor::BrightMagenta,
+                NamedColor::BrightCyan,
+                NamedColor::BrightWhite,
+            ],
+        };
+        colors[usize::from(base & 7)]
+    }
+
+    fn index(&self) -> (u8, bool) {
+        match self {
+            NamedColor::Black => (0, false),
+            NamedColor::Red => (1, false),
+            NamedColor::Green => (2, false),
+            NamedColor::Yellow => (3, false),
+            NamedColor::Blue => (4, false),
+            NamedColor::Magenta => (5, false),
+            NamedColor::Cyan => (6, false),
+            NamedColor::White => (7, false),
+            NamedColor::BrightBlack => (0, true),
+            NamedColor::BrightRed => (1, true),
+            NamedColor::BrightGreen => (2, true),
+            NamedColor::BrightYellow => (3, true),
+            NamedColor::BrightBlue => (4, true),
+            NamedColor::BrightMagenta => (5, true),
+            NamedColor::BrightCyan => (6, true),
+            NamedColor::BrightWhite => (7, true),
+        }
+    }
+}
+
+/// A terminal color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the sixteen standard colors
+    Named(NamedColor),
+    /// An index into the 256-color palette
+    Ansi256(u8),
+    /// A 24-bit true color
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// The escape sequence that sets this color as the foreground
+    pub fn fg(&self) -> String {
+        match self {
+            Color::Named(named) => format!("\u{001B}[{}m", named.fg_code()),
+            Color::Ansi256(index) => format!("\u{001B}[38;5;{}m", index),
+            Color::Rgb(r, g, b) => format!("\u{001B}[38;2;{};{};{}m", r, g, b),
+        }
+    }
+
+    /// The escape sequence that sets this color as the background
+    pub fn bg(&self) -> String {
+        match self {
+            Color::Named(named) => format!("\u{001B}[{}m", named.bg_code()),
+            Color::Ansi256(index) => format!("\u{001B}[48;5;{}m", index),
+            Color::Rgb(r, g, b) => format!("\u{001B}[48;2;{};{};{}m", r, g, b),
+        }
+    }
+}
+
+/// A separate color for each edge of the border.
+///
+/// The top and bottom edges own the corners, so `┌` and `┐` take the top
+/// color and `└` and `┘` the bottom one. The left and right colors cover the
+/// vertical runs between them, including the tees where separators meet the
+/// sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeColors {
+    pub top: Color,
+    pub right: Color,
+    pub bottom: Color,
+    pub left: Color,
+}
+
+/// The shape of a box's corners, independent of its edge style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornerStyle {
+    /// Square corners; rounded corners of the style become `┌┐└┘`
+    Sharp,
+    /// Rounded corners (`╭╮╰╯`)
+    Round,
+    /// A 45° chamfer (`╱╲`)
+    Cut,
+}
+
+impl CornerStyle {
+    /// `chars` with its four corners drawn in this style
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::{BorderStyle, CornerStyle};
+    ///
+    /// let chars = CornerStyle::Cut.apply(BorderStyle::Double.chars());
+    /// assert_eq!((chars.top_left, chars.horizontal), ("╱", "═"));
+    /// ```
+    pub fn apply(self, chars: BorderChars) -> BorderChars {
+        let sharpen = |corner: &'static str| match corner {
+            "╭" => "┌",
+            "╮" => "┐",
+            "╰" => "└",
+            "╯" => "┘",
+            corner => corner,
+        };
+        let [top_left, top_right, bottom_left, bottom_right] = match self {
+            CornerStyle::Sharp => [
+                sharpen(chars.top_left),
+                sharpen(chars.top_right),
+                sharpen(chars.bottom_left),
+                sharpen(chars.bottom_right),
+            ],
+            CornerStyle::Round => ["╭", "╮", "╰", "╯"],
+            CornerStyle::Cut => ["╱", "╲", "╲", "╱"],
+        };
+        BorderChars {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            ..chars
+        }
+    }
+}
+
+/// A [`CornerStyle`] for each corner of a box, e.g. round top corners over
+/// sharp bottom ones for a tab or card look
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CornerQuad {
+    pub top_left: CornerStyle,
+    pub top_right: CornerStyle,
+    pub bottom_left: CornerStyle,
+    pub bottom_right: CornerStyle,
+}
+
+/// Which way content reads inside the box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    #[default]
+    Horizontal,
+    /// Rotated a quarter turn clockwise: each line reads top to bottom, and
+    /// the first line is the rightmost column
+    VerticalCw,
+    /// Rotated a quarter turn counter-clockwise: each line reads bottom to
+    /// top, and the first line is the leftmost column
+    VerticalCcw,
+}
+
+/// How content wider than the available space is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Content is drawn in full, word-wrapped where it would make the box
+    /// wider than `max_width`
+    #[default]
+    Visible,
+    /// The box, margins included, is clipped to the terminal width, showing a
+    /// horizontal window of the content starting at `scroll_x`, with `◀`/`▶`
+    /// marking rows that continue beyond the window
+    Scroll,
+    /// Content is never wrapped or clipped; [`try_boxen`] fails with an
+    /// [`OverflowError`] instead, while [`boxen`] draws it in full
+    Error,
+}
+
+/// A content line too wide for the box, reported by [`try_boxen`] under
+/// [`Overflow::Error`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverflowError {
+    /// The index of the offending line in the input text
+    pub line: usize,
+    /// The display width of that line
+    pub width: usize,
+    /// The widest a content line could be, once borders and padding are
+    /// taken from the box width
+    pub limit: usize,
+}
+
+impl std::fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {} is {} columns wide, but the box fits at most {}",
+            self.line + 1,
+            self.width,
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+/// Interior grid lines for tabular content
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GridSpec {
+    /// Interior columns (counting from the cell right of the left border,
+    /// padding included) where a vertical grid line is drawn
+    pub columns: Vec<usize>,
+}
+
+impl GridSpec {
+    /// Create a grid with vertical lines at the given interior columns
+    pub fn new(columns: Vec<usize>) -> Self {
+        Self { columns }
+    }
+}
+
+/// Resets the foreground color to the terminal default
+const FG_RESET: &str = "\u{001B}[39m";
+const BG_RESET: &str = "\u{001B}[49m";
+const REVERSE: &str = "\u{001B}[7m";
+const REVERSE_OFF: &str = "\u{001B}[27m";
+
+/// Options for drawing a box
+#[derive(Debug, Clone)]
+pub struct BoxenOptions {
+    /// The border style; when unset, `BOXEN_STYLE` is consulted before
+    /// falling back to single
+    pub border_style: Option<BorderStyle>,
+    /// Space between the border and the content
+    pub padding: Spacing,
+    /// Space outside the border; when `margin_x` is set it replaces the left
+    /// and right values here, and only `top` and `bottom` are used
+    pub margin: Spacing,
+    /// The left and right margin in cells or as a percentage of the terminal
+    /// width, replacing `margin.left` and `margin.right` when set
+    pub margin_x: Option<Dimension>,
+    /// How content lines are aligned inside the box; `Smart` right-aligns
+    /// numeric lines and left-aligns the rest (default: left)
+    pub text_alignment: Alignment,
+    /// Expand tabs in content to stops this many columns apart, counted
+    /// from the interior's left edge, before anything is measured; 0 leaves
+    /// tabs untouched (default: 0)
+    pub tab_width: usize,
+    /// Also break content lines at the Unicode line and paragraph separators
+    /// (`\u{2028}`, `\u{2029}`), not just `\n` (default: false)
+    pub unicode_line_breaks: bool,
+    /// Convert inline tags in content to ANSI styling before anything is
+    /// measured: `[red]`...`[/]` for the eight standard colors, and
+    /// `[bold]`, `[dim]`. `[/]` closes the most recent tag; anything else in
+    /// brackets is left as is (default: false)
+    pub markup: bool,
+    /// Frame content as a code snippet: every line keeps its whitespace
+    /// verbatim and sits against the left edge whatever `text_alignment` or
+    /// `rtl` say. `tab_width` still expands tabs, but `markup` and
+    /// `trim_blank_lines` are ignored, and lines too long for a fixed width
+    /// break at the edge instead of between words (default: false)
+    pub code_block: bool,
+    /// Content line indices after which a horizontal divider row is drawn
+    pub separators: Vec<usize>,
+    /// The color of the border glyphs
+    pub border_color: Option<Color>,
+    /// Per-edge border colors; these override `border_color` on the outer
+    /// edges, which still colors separators and grid lines
+    pub border_colors: Option<EdgeColors>,
+    /// A title drawn in the top border; newlines are replaced by spaces
+    pub title: Option<String>,
+    /// The color of the title text, independent of `border_color`
+    pub title_color: Option<Color>,
+    /// Where the title sits along the top border (default: left)
+    pub title_alignment: Alignment,
+    /// A line of text drawn below the bottom border, outside the frame, like
+    /// a figure caption; newlines are replaced by spaces. It never widens
+    /// the box and is cut to the box's width when longer
+    pub caption: Option<String>,
+    /// Where the caption sits under the box (default: left)
+    pub caption_alignment: Alignment,
+    /// When a title widens the box, align content across the full widened
+    /// interior instead of keeping it against its own widest line
+    pub content_follows_title_width: bool,
+    /// How content wider than the terminal is handled (default: visible)
+    pub overflow: Overflow,
+    /// The first content column shown when `overflow` is `Scroll`
+    pub scroll_x: usize,
+    /// The terminal width to lay out against; detected when unset
+    pub terminal_width: Option<usize>,
+    /// Whether output goes to a terminal; detected from stdout when unset
+    pub is_tty: Option<bool>,
+    /// Draw with the `Ascii` style, whatever `border_style` says, when output
+    /// isn't a terminal, so piped or redirected boxes stay readable in plain
+    /// files (default: false)
+    pub ascii_when_piped: bool,
+    /// Vertical grid lines drawn through the interior
+    pub grid: Option<GridSpec>,
+    /// Draw a column ruler above the box and append each content line's
+    /// measured width, for diagnosing ragged output (default: false)
+    pub debug: bool,
+    /// Indent every output row, margins included, by this many columns to
+    /// place the box at an absolute screen column
+    pub anchor_column: Option<usize>,
+    /// The column the cursor is already at when the box is printed, e.g.
+    /// after a shell prompt or label. The first row's indent from
+    /// `anchor_column` and `box_alignment` is reduced by this much so it
+    /// lines up with the rows below, which keep the full indent (default: 0)
+    pub initial_column: usize,
+    /// Where the whole box, margins included, sits across the terminal
+    /// width, mirrored by `rtl` so a right-to-left box sits against the right
+    /// edge (default: left). The name used by the JavaScript `boxen`; see
+    /// `box_alignment`
+    pub float: Alignment,
+    /// Where the whole box sits across the terminal width, the clearer name
+    /// for `float`; when both are set away from left, this one wins
+    /// (default: left)
+    pub box_alignment: Alignment,
+    /// A fixed total width for the box, borders included, in cells or as a
+    /// percentage of the terminal width; longer content lines are
+    /// word-wrapped to fit
+    pub width: Option<Dimension>,
+    /// The widest the box may grow, borders included; content lines that
+    /// would exceed it are word-wrapped while narrower boxes stay narrow.
+    /// Defaults to the terminal width less margins
+    pub max_width: Option<usize>,
+    /// Mirror the layout for right-to-left interfaces: alignments, padding,
+    /// margins, grid columns and the box's place across the terminal all
+    /// count from the right (default: false)
+    pub rtl: bool,
+    /// Round the interior width up to an even number of columns, so rows of
+    /// double-width characters fill it exactly; the box may grow by one
+    /// column (default: false)
+    pub even_width: bool,
+    /// Render nothing at all, rather than an empty box, when the content is
+    /// only whitespace (default: false)
+    pub hide_if_empty: bool,
+    /// Drop whitespace-only lines from the start and end of the content;
+    /// blank lines between content are kept (default: false)
+    pub trim_blank_lines: bool,
+    /// The background of the interior, padding included; the border itself
+    /// is never colored by it
+    pub background_color: Option<Color>,
+    /// Alternate the background of content rows between two colors, starting
+    /// with the first; padding rows use the first color. Takes precedence
+    /// over `background_color`
+    pub zebra: Option<(Color, Color)>,
+    /// Fill this share (0.0 to 1.0) of every interior row from the left, so
+    /// the box reads as a labeled progress bar with the content on top. A
+    /// wide character straddling the boundary is filled
+    pub fill_ratio: Option<f32>,
+    /// The background of the `fill_ratio` fill (default: reverse video)
+    pub fill_color: Option<Color>,
+    /// Surround the box with a mat this many cells deep on every side,
+    /// filled with the color as background and drawn without a border.
+    /// Unlike a margin it is painted; margins still go outside it
+    pub bleed: Option<(usize, Color)>,
+    /// How many cells thick the border is; extra thickness is drawn as
+    /// nested rings of the border style, with the title on the innermost
+    /// (default: 1)
+    pub border_width: usize,
+    /// Which way content reads; vertical orientations turn each line into
+    /// a column (default: horizontal)
+    pub orientation: Orientation,
+    /// Draw the corners as filled quarter blocks (`▛▜▙▟`) for a softer
+    /// look, keeping the style's edges (default: false)
+    pub corner_fill: bool,
+    /// Draw the corners in this shape whatever the border style's edges,
+    /// e.g. round corners on a double border; `corner_fill` takes
+    /// precedence (default: the style's own corners)
+    pub corner_style: Option<CornerStyle>,
+    /// Draw each corner in its own shape; overrides `corner_style`, while
+    /// `corner_fill` still takes precedence (default: the style's own
+    /// corners)
+    pub corner_styles: Option<CornerQuad>,
+}
+
+impl Default for BoxenOptions {
+    fn default() -> Self {
+        Self {
+            border_style: None,
+            padding: Spacing::default(),
+            margin: Spacing::default(),
+            margin_x: None,
+            text_alignment: Alignment::Left,
+            tab_width: 0,
+            unicode_line_breaks: false,
+            markup: false,
+            code_block: false,
+            separators: Vec::new(),
+            border_color: None,
+            border_colors: None,
+            title: None,
+            title_color: None,
+            title_alignment: Alignment::Left,
+            caption: None,
+            caption_alignment: Alignment::Left,
+            content_follows_title_width: false,
+            overflow: Overflow::default(),
+            scroll_x: 0,
+            terminal_width: None,
+            is_tty: None,
+            ascii_when_piped: false,
+            grid: None,
+            debug: false,
+            anchor_column: None,
+            initial_column: 0,
+            float: Alignment::Left,
+            box_alignment: Alignment::Left,
+            width: None,
+            max_width: None,
+            rtl: false,
+            even_width: false,
+            hide_if_empty: false,
+            trim_blank_lines: false,
+            background_color: None,
+            zebra: None,
+            fill_ratio: None,
+            fill_color: None,
+            bleed: None,
+            border_width: 1,
+            orientation: Orientation::default(),
+            corner_fill: false,
+            corner_style: None,
+            corner_styles: None,
+        }
+    }
+}
+
+impl BoxenOptions {
+    /// Create new options with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the border style
+    pub fn border_style(mut self, border_style: BorderStyle) -> Self {
+        self.border_style = Some(border_style);
+        self
+    }
+
+    /// Set the padding
+    ///
+    /// A bare number expands like `Spacing::scalar`.
+    pub fn padding(mut self, padding: impl Into<Spacing>) -> Self {
+        self.padding = padding.into();
+        self
+    }
+
+    /// Set the margin
+    ///
+    /// A bare number expands like `Spacing::scalar`. The left and right
+    /// values are ignored while `margin_x` is set.
+    pub fn margin(mut self, margin: impl Into<Spacing>) -> Self {
+        self.margin = margin.into();
+        self
+    }
+
+    /// Set the text alignment
+    pub fn text_alignment(mut self, text_alignment: Alignment) -> Self {
+        self.text_alignment = text_alignment;
+        self
+    }
+
+    /// Set the tab stop width used to expand tabs in content
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Set whether the Unicode line and paragraph separators break lines
+    pub fn unicode_line_breaks(mut self, unicode_line_breaks: bool) -> Self {
+        self.unicode_line_breaks = unicode_line_breaks;
+        self
+    }
+
+    /// Set whether inline `[red]`...`[/]` tags in content are converted
+    pub fn markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Set whether content is framed verbatim as a code snippet
+    pub fn code_block(mut self, code_block: bool) -> Self {
+        self.code_block = code_block;
+        self
+    }
+
+    /// Set the content line indices to draw separators after
+    pub fn separators(mut self, separators: Vec<usize>) -> Self {
+        self.separators = separators;
+        self
+    }
+
+    /// Set the border color
+    pub fn border_color(mut self, border_color: Color) -> Self {
+        self.border_color = Some(border_color);
+        self
+    }
+
+    /// Set a separate color for each edge of the border
+    pub fn border_colors(mut self, border_colors: EdgeColors) -> Self {
+        self.border_colors = Some(border_colors);
+        self
+    }
+
+    /// Set the title
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the title color
+    pub fn title_color(mut self, title_color: Color) -> Self {
+        self.title_color = Some(title_color);
+        self
+    }
+
+    /// Set the title alignment
+    pub fn title_alignment(mut self, title_alignment: Alignment) -> Self {
+        self.title_alignment = title_alignment;
+        self
+    }
+
+    /// Set the caption drawn below the box
+    pub fn caption<S: Into<String>>(mut self, caption: S) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+
+    /// Set the caption alignment
+    pub fn caption_alignment(mut self, caption_alignment: Alignment) -> Self {
+        self.caption_alignment = caption_alignment;
+        self
+    }
+
+    /// Set whether content re-aligns to a title-widened interior
+    pub fn content_follows_title_width(mut self, content_follows_title_width: bool) -> Self {
+        self.content_follows_title_width = content_follows_title_width;
+        self
+    }
+
+    /// Set the overflow behavior
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Set the horizontal scroll offset, in columns
+    pub fn scroll_x(mut self, scroll_x: usize) -> Self {
+        self.scroll_x = scroll_x;
+        self
+    }
+
+    /// Set the terminal width instead of detecting it
+    pub fn terminal_width(mut self, terminal_width: usize) -> Self {
+        self.terminal_width = Some(terminal_width);
+        self
+    }
+
+    /// Set whether output goes to a terminal instead of detecting it
+    pub fn is_tty(mut self, is_tty: bool) -> Self {
+        self.is_tty = Some(is_tty);
+        self
+    }
+
+    /// Set whether piped output falls back to the ASCII style
+    pub fn ascii_when_piped(mut self, ascii_when_piped: bool) -> Self {
+        self.ascii_when_piped = ascii_when_piped;
+        self
+    }
+
+    /// Set the interior grid lines
+    pub fn grid(mut self, grid: GridSpec) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
+    /// Set whether the width-diagnosing ruler and annotations are drawn
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Set the screen column the box is drawn from
+    pub fn anchor_column(mut self, anchor_column: usize) -> Self {
+        self.anchor_column = Some(anchor_column);
+        self
+    }
+
+    /// Set the column the first row starts printing at
+    pub fn initial_column(mut self, initial_column: usize) -> Self {
+        self.initial_column = initial_column;
+        self
+    }
+
+    /// Set where the box sits across the terminal, as `box_alignment`
+    pub fn float(mut self, float: Alignment) -> Self {
+        self.float = float;
+        self
+    }
+
+    /// Set where the box sits across the terminal
+    pub fn box_alignment(mut self, box_alignment: Alignment) -> Self {
+        self.box_alignment = box_alignment;
+        self
+    }
+
+    /// Set a fixed total width for the box
+    ///
+    /// A bare number is a count of cells.
+    pub fn width(mut self, width: impl Into<Dimension>) -> Self {
+        self.width = Some(width.into());
+        self
+    }
+
+    /// Set the left and right margin, in cells or as a percentage of the
+    /// terminal width
+    pub fn margin_x(mut self, margin_x: impl Into<Dimension>) -> Self {
+        self.margin_x = Some(margin_x.into());
+        self
+    }
+
+    /// Set the widest the box may grow
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Set whether the box is laid out right to left
+    pub fn rtl(mut self, rtl: bool) -> Self {
+        self.rtl = rtl;
+        self
+    }
+
+    /// Set whether the interior width is rounded up to an even number
+    pub fn even_width(mut self, even_width: bool) -> Self {
+        self.even_width = even_width;
+        self
+    }
+
+    /// Set whether whitespace-only content renders as an empty string
+    pub fn hide_if_empty(mut self, hide_if_empty: bool) -> Self {
+        self.hide_if_empty = hide_if_empty;
+        self
+    }
+
+    /// Set whether leading and trailing blank lines are dropped
+    pub fn trim_blank_lines(mut self, trim_blank_lines: bool) -> Self {
+        self.trim_blank_lines = trim_blank_lines;
+        self
+    }
+
+    /// Set the interior background color
+    pub fn background_color(mut self, background_color: Color) -> Self {
+        self.background_color = Some(background_color);
+        self
+    }
+
+    /// Set the two alternating content row backgrounds
+    pub fn zebra(mut self, primary: Color, secondary: Color) -> Self {
+        self.zebra = Some((primary, secondary));
+        self
+    }
+
+    /// Set the share of the interior filled as a progress bar
+    pub fn fill_ratio(mut self, fill_ratio: f32) -> Self {
+        self.fill_ratio = Some(fill_ratio);
+        self
+    }
+
+    /// Set the background color of the progress bar fill
+    pub fn fill_color(mut self, fill_color: Color) -> Self {
+        self.fill_color = Some(fill_color);
+        self
+    }
+
+    /// Set the depth and color of the mat around the box
+    pub fn bleed(mut self, cells: usize, color: Color) -> Self {
+        self.bleed = Some((cells, color));
+        self
+    }
+
+    /// Set how many cells thick the border is
+    pub fn border_width(mut self, border_width: usize) -> Self {
+        self.border_width = border_width;
+        self
+    }
+
+    /// Set which way content reads
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Set whether corners are drawn as filled quarter blocks
+    pub fn corner_fill(mut self, corner_fill: bool) -> Self {
+        self.corner_fill = corner_fill;
+        self
+    }
+
+    /// Set the shape of the corners
+    pub fn corner_style(mut self, corner_style: CornerStyle) -> Self {
+        self.corner_style = Some(corner_style);
+        self
+    }
+
+    /// Set the shape of each corner separately
+    pub fn corner_styles(mut self, corner_styles: CornerQuad) -> Self {
+        self.corner_styles = Some(corner_styles);
+        self
+    }
+
+    /// Layer `patch` over these options: every field the patch sets replaces
+    /// the base value, and everything else is kept
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use boxen::{BorderStyle, BoxenOptions, BoxenOptionsPatch, Color, NamedColor};
+    ///
+    /// let theme = BoxenOptions::new().border_style(BorderStyle::Round).padding(1);
+    /// let patch = BoxenOptionsPatch {
+    ///     border_color: Some(Color::Named(NamedColor::Red)),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let merged = theme.merge(&patch);
+    /// assert_eq!(merged.border_style, Some(BorderStyle::Round));
+    /// assert_eq!(merged.border_color, Some(Color::Named(NamedColor::Red)));
+    /// ```
+    pub fn merge(&self, patch: &BoxenOptionsPatch) -> BoxenOptions {
+        BoxenOptions {
+            border_style: patch.border_style.or(self.border_style),
+            padding: patch.padding.unwrap_or(self.padding),
+            margin: patch.margin.unwrap_or(self.margin),
+            margin_x: patch.margin_x.or(self.margin_x),
+            text_alignment: patch.text_alignment.unwrap_or(self.text_alignment),
+            tab_width: patch.tab_width.unwrap_or(self.tab_width),
+            unicode_line_breaks: patch
+                .unicode_line_breaks
+                .unwrap_or(self.unicode_line_breaks),
+            markup: patch.markup.unwrap_or(self.markup),
+            code_block: patch.code_block.unwrap_or(self.code_block),
+            separators: patch
+                .separators
+                .clone()
+                .unwrap_or_else(|| self.separators.clone()),
+            border_color: patch.border_color.or(self.border_color),
+            border_colors: patch.border_colors.or(self.border_colors),
+            title: patch.title.clone().or_else(|| self.title.clone()),
+            title_color: patch.title_color.or(self.title_color),
+            title_alignment: patch.title_alignment.unwrap_or(self.title_alignment),
+            caption: patch.caption.clone().or_else(|| self.caption.clone()),
+            caption_alignment: patch.caption_alignment.unwrap_or(self.caption_alignment),
+            content_follows_title_width: patch
+                .content_follows_title_width
+                .unwrap_or(self.content_follows_title_width),
+            overflow: patch.overflow.unwrap_or(self.overflow),
+            scroll_x: patch.scroll_x.unwrap_or(self.scroll_x),
+            terminal_width: patch.terminal_width.or(self.terminal_width),
+            is_tty: patch.is_tty.or(self.is_tty),
+            ascii_when_piped: patch.ascii_when_piped.unwrap_or(self.ascii_when_piped),
+            grid: patch.grid.clone().or_else(|| self.grid.clone()),
+            debug: patch.debug.unwrap_or(self.debug),
+            anchor_column: patch.anchor_column.or(self.anchor_column),
+            initial_column: patch.initial_column.unwrap_or(self.initial_column),
+            float: patch.float.unwrap_or(self.float),
+            box_alignment: patch.box_alignment.unwrap_or(self.box_alignment),
+            width: patch.width.or(self.width),
+            max_width: patch.max_width.or(self.max_width),
+            rtl: patch.rtl.unwrap_or(self.rtl),
+            even_width: patch.even_width.unwrap_or(self.even_width),
+            hide_if_empty: patch.hide_if_empty.unwrap_or(self.hide_if_empty),
+            trim_blank_lines: patch.trim_blank_lines.unwrap_or(self.trim_blank_lines),
+            background_color: patch.background_color.or(self.background_color),
+            zebra: patch.zebra.or(self.zebra),
+            fill_ratio: patch.fill_ratio.or(self.fill_ratio),
+            fill_color: patch.fill_color.or(self.fill_color),
+            bleed: patch.bleed.or(self.bleed),
+            border_width: patch.border_width.unwrap_or(self.border_width),
+            orientation: patch.orientation.unwrap_or(self.orientation),
+            corner_fill: patch.corner_fill.unwrap_or(self.corner_fill),
+            corner_style: patch.corner_style.or(self.corner_style),
+            corner_styles: patch.corner_styles.or(self.corner_styles),
+        }
+    }
+}
+
+/// A partial set of [`BoxenOptions`] for [`BoxenOptions::merge`]; each field
+/// left as `None` keeps the base value
+#[derive(Debug, Clone, Default)]
+pub struct BoxenOptionsPatch {
+    pub border_style: Option<BorderStyle>,
+    pub padding: Option<Spacing>,
+    pub margin: Option<Spacing>,
+    pub margin_x: Option<Dimension>,
+    pub text_alignment: Option<Alignment>,
+    pub tab_width: Option<usize>,
+    pub unicode_line_breaks: Option<bool>,
+    pub markup: Option<bool>,
+    pub code_block: Option<bool>,
+    pub separators: Option<Vec<usize>>,
+    pub border_color: Option<Color>,
+    pub border_colors: Option<EdgeColors>,
+    pub title: Option<String>,
+    pub title_color: Option<Color>,
+    pub title_alignment: Option<Alignment>,
+    pub caption: Option<String>,
+    pub caption_alignment: Option<Alignment>,
+    pub content_follows_title_width: Option<bool>,
+    pub overflow: Option<Overflow>,
+    pub scroll_x: Option<usize>,
+    pub terminal_width: Option<usize>,
+    pub is_tty: Option<bool>,
+    pub ascii_when_piped: Option<bool>,
+    pub grid: Option<GridSpec>,
+    pub debug: Option<bool>,
+    pub anchor_column: Option<usize>,
+    pub initial_column: Option<usize>,
+    pub float: Option<Alignment>,
+    pub box_alignment: Option<Alignment>,
+    pub width: Option<Dimension>,
+    pub max_width: Option<usize>,
+    pub rtl: Option<bool>,
+    pub even_width: Option<bool>,
+    pub hide_if_empty: Option<bool>,
+    pub trim_blank_lines: Option<bool>,
+    pub background_color: Option<Color>,
+    pub zebra: Option<(Color, Color)>,
+    pub fill_ratio: Option<f32>,
+    pub fill_color: Option<Color>,
+    pub bleed: Option<(usize, Color)>,
+    pub border_width: Option<usize>,
+    pub orientation: Option<Orientation>,
+    pub corner_fill: Option<bool>,
+    pub corner_style: Option<CornerStyle>,
+    pub corner_styles: Option<CornerQuad>,
+}
+
+/// Ready-made looks for common CLI status messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Info,
+    Warning,
+    Error,
+    Success,
+}
+
+impl Theme {
+    /// The glyph prefixed to the theme's title
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Theme::Info => "ℹ",
+            Theme::Warning => "⚠",
+            Theme::Error => "✖",
+            Theme::Success => "✔",
+        }
+    }
+
+    /// The theme's border color
+    pub fn color(&self) -> Color {
+        match self {
+            Theme::Info => Color::Named(NamedColor::Blue),
+            Theme::Warning => Color::Named(NamedColor::Yellow),
+            Theme::Error => Color::Named(NamedColor::Red),
+            Theme::Success => Color::Named(NamedColor::Green),
+        }
+    }
+
+    /// The theme's default title, e.g. `⚠ Warning`
+    pub fn title(&self) -> String {
+        let label = match self {
+            Theme::Info => "Info",
+            Theme::Warning => "Warning",
+            Theme::Error => "Error",
+            Theme::Success => "Success",
+        };
+        format!("{} {}", self.glyph(), label)
+    }
+
+    /// Resolve the theme to box options
+    pub fn options(&self) -> BoxenOptions {
+        BoxenOptions::new()
+            .border_style(BorderStyle::Round)
+            .border_color(self.color())
+            .title(self.title())
+            .padding(Spacing {
+                top: 0,
+                right: 1,
+                bottom: 0,
+                left: 1,
+            })
+    }
+}
+
+/// Detect the terminal width from the `COLUMNS` environment variable,
+/// falling back to 80 columns.
+pub fn terminal_width() -> usize {
+    ansi_align::terminal_width()
+}
+
+/// The terminal width laid out against when the options leave it unset;
+/// unit tests use 80 columns rather than [`terminal_width`], so they render
+/// the same whatever the shell exports
+fn fallback_columns() -> usize {
+    match cfg!(test) {
+        true => 80,
+        false => terminal_width(),
+    }
+}
+
+/// The border style used when `BoxenOptions::border_style` is unset: the
+/// `BOXEN_STYLE` environment variable if it names a preset, otherwise single.
+pub fn default_border_style() -> BorderStyle {
+    parse_style(std::env::var("BOXEN_STYLE").ok().as_deref())
+}
+
+/// The border style a `BOXEN_STYLE` value names, or single if it is unset or
+/// names no preset
+fn parse_style(value: Option<&str>) -> BorderStyle {
+    value
+        .and_then(|style| style.parse().ok())
+        .unwrap_or_default()
+}
+
+/// The border style drawn when the options leave it unset; unit tests use
+/// single rather than [`default_border_style`], so they render the same
+/// whatever the shell exports
+fn fallback_border_style() -> BorderStyle {
+    match cfg!(test) {
+        true => BorderStyle::default(),
+        false => default_border_style(),
+    }
+}
+
+/// A piece of a rendered line: an ANSI escape sequence or a grapheme
+/// cluster with its display width
+enum Piece<'a> {
+    Escape(&'a str),
+    Grapheme(&'a str, usize),
+}
+
+/// Split `s` into escape sequences and width-measured grapheme clusters
+fn pieces<'a>(s: &'a str, strategy: &dyn WidthStrategy) -> Vec<Piece<'a>> {
+    let mut out = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        if rest.starts_with('\u{001B}') {
+            let len = ansi_sequence_len(rest);
+            out.push(Piece::Escape(&rest[..len]));
+            rest = &rest[len..];
+        } else {
+            let end = rest.find('\u{001B}').unwrap_or(rest.len());
+            out.extend(
+                rest[..end]
+                    .graphemes(true)
+                    .map(|grapheme| Piece::Grapheme(grapheme, strategy.width(grapheme))),
+            );
+            rest = &rest[end..];
+        }
+    }
+    out
+}
+
+/// Replace the cell at `column` of `row` with the width-1 `glyph`, keeping
+/// any ANSI sequences intact. A wide character covering `column` is
+/// replaced by the glyph and a space so the row width is unchanged.
+fn overlay_cell(row: &str, column: usize, glyph: &str, strategy: &dyn WidthStrategy) -> String {
+    let mut out = String::with_capacity(row.len() + glyph.len());
+    let mut current = 0;
+    for piece in pieces(row, strategy) {
+        match piece {
+            Piece::Escape(escape) => out.push_str(escape),
+            Piece::Grapheme(grapheme, width) => {
+                if width > 0 && current <= column && column < current + width {
+                    out.push_str(&" ".repeat(column - current));
+                    out.push_str(glyph);
+                    out.push_str(&" ".repeat(current + width - column - 1));
+                } else {
+                    out.push_str(grapheme);
+                }
+                current += width;
+            }
+        }
+    }
+    out
+}
+
+/// Split `row` into the part covering its first `column` columns and the
+/// rest; a wide grapheme straddling `column` goes into the first part.
+fn split_at_column(row: &str, column: usize, strategy: &dyn WidthStrategy) -> (String, String) {
+    let mut head = String::new();
+    let mut tail = String::new();
+    let mut current = 0;
+    for piece in pieces(row, strategy) {
+        let (text, width) = match piece {
+            Piece::Escape(escape) => (escape, 0),
+            Piece::Grapheme(grapheme, width) => (grapheme, width),
+        };
+        match current < column {
+            true => head.push_str(text),
+            false => tail.push_str(text),
+        }
+        current += width;
+    }
+    (head, tail)
+}
+
+/// Take the `width` columns of `line` starting at column `start`.
+///
+/// ANSI sequences are dropped and a wide character straddling either edge
+/// of the window is replaced by spaces, so the result is exactly `width` wide.
+fn clip_columns(line: &str, start: usize, width: usize, strategy: &dyn WidthStrategy) -> String {
+    let end = start + width;
+    let mut out = String::new();
+    let mut visible = 0;
+    let mut column = 0;
+    for piece in pieces(line, strategy) {
+        if let Piece::Grapheme(grapheme, grapheme_width) = piece {
+            if column + grapheme_width > end {
+                break;
+            }
+            if column >= start {
+                out.push_str(grapheme);
+                visible += grapheme_width;
+            } else if column + grapheme_width > start {
+                out.push_str(&" ".repeat(column + grapheme_width - start));
+                visible += column + grapheme_width - start;
+            }
+            column += grapheme_width;
+        }
+    }
+    out.push_str(&" ".repeat(width - visible));
+    out
+}
+
+/// Transpose lines into columns for vertical orientations.
+///
+/// Every grapheme takes one row. Each column is as wide as the widest
+/// grapheme in its source line, so CJK columns are two cells wide and
+/// narrower graphemes in them are padded. Escape sequences are dropped.
+fn rotate(
+    lines: &[(Cow<str>, usize)],
+    orientation: Orientation,
+    strategy: &dyn WidthStrategy,
+) -> Vec<String> {
+    let mut columns: Vec<Vec<(&str, usize)>> = lines
+        .iter()
+        .map(|(line, _)| {
+            pieces(line, strategy)
+                .into_iter()
+                .filter_map(|piece| match piece {
+                    Piece::Grapheme(grapheme, width) => Some((grapheme, width)),
+                    Piece::Escape(_) => None,
+                })
+                .collect()
+        })
+        .collect();
+    if orientation == Orientation::VerticalCw {
+        columns.reverse();
+    }
+
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|column| column.iter().map(|(_, width)| *width).max().unwrap_or(1))
+        .collect();
+    (0..height)
+        .map(|row| {
+            columns
+                .iter()
+                .zip(&widths)
+                .map(|(column, &column_width)| {
+                    let index = match orientation {
+                        Orientation::VerticalCcw => height - 1 - row,
+                        _ => row,
+                    };
+                    match column.get(index) {
+                        Some((grapheme, width)) => {
+                            format!("{}{}", grapheme, " ".repeat(column_width - width))
+                        }
+                        None => " ".repeat(column_width),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+const SGR_RESET: &str = "\u{001B}[0m";
+
+/// Whether `escape` is an SGR sequence (`ESC [ ... m`)
+fn is_sgr(escape: &str) -> bool {
+    escape.starts_with("\u{001B}[") && escape.ends_with('m')
+}
+
+/// Whether `escape` switches off the attribute whose off code is `off`,
+/// directly or through a full reset
+fn sgr_clears(escape: &str, off: &str) -> bool {
+    if !is_sgr(escape) {
+        return false;
+    }
+    let mut params = escape[2..escape.len() - 1].split(';');
+    while let Some(param) = params.next() {
+        match param {
+            "" | "0" => return true,
+            // Skip the arguments of extended colors, which may well be 0
+            "38" | "48" => match params.next() {
+                Some("5") => {
+                    params.next();
+                }
+                Some("2") => params.by_ref().take(3).for_each(drop),
+                _ => {}
+            },
+            param if param == off => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Re-emit `on` after every escape in `text` that would switch it off (see
+/// [`sgr_clears`]), so a reset inside styled content can't end an
+/// enclosing style early
+fn reassert_sgr(text: &str, on: &str, off: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('\u{001B}') {
+        let len = ansi_sequence_len(&rest[start..]);
+        let escape = &rest[start..start + len];
+        out.push_str(&rest[..start + len]);
+        if sgr_clears(escape, off) {
+            out.push_str(on);
+        }
+        rest = &rest[start + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The SGR parameters that open and close a markup tag
+fn markup_tag(name: &str) -> Option<(u8, u8)> {
+    const COLORS: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    match name {
+        "bold" => Some((1, 22)),
+        "dim" => Some((2, 22)),
+        _ => COLORS
+            .iter()
+            .position(|color| *color == name)
+            .map(|index| (30 + index as u8, 39)),
+    }
+}
+
+/// Replace the markup tags in `line` with SGR sequences.
+///
+/// `open` holds the tags still open, outermost first, and carries them from
+/// one line to the next. Closing a tag switches its attribute off and back
+/// to any outer tag that set the same one, so `[red][blue]a[/]b[/]` leaves
+/// `b` red.
+fn apply_markup(line: &str, open: &mut Vec<(u8, u8)>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        match &rest[1..end] {
+            "/" if !open.is_empty() => {
+                let (_, off) = open.pop().unwrap_or_default();
+                out.push_str(&format!("\u{001B}[{}m", off));
+                if let Some((outer, _)) = open.iter().rev().find(|(_, other)| *other == off) {
+                    out.push_str(&format!("\u{001B}[{}m", outer));
+                }
+            }
+            name => match markup_tag(name) {
+                Some((on, off)) => {
+                    open.push((on, off));
+                    out.push_str(&format!("\u{001B}[{}m", on));
+                }
+                None => {
+                    out.push('[');
+                    rest = &rest[1..];
+                    continue;
+                }
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Update `active`, the SGR parameters in effect, with the SGR sequences in
+/// `line`.
+///
+/// Parameters are grouped by the attribute they set (foreground,
+/// background, weight, ...): setting one replaces any earlier parameter in
+/// its group, its "off" code (`39`, `22`, ...) removes the group, and `0`
+/// clears everything. Extended colors such as `38;5;n` are kept whole.
+fn track_sgr(active: &mut Vec<String>, line: &str) {
+    // The "off" code of the attribute group each parameter belongs to
+    let group = |code: &str| match code.split(';').next()?.parse::<u16>().ok()? {
+        1 | 2 | 22 => Some(22),
+        3 | 23 => Some(23),
+        4 | 21 | 24 => Some(24),
+        5 | 6 | 25 => Some(25),
+        7 | 27 => Some(27),
+        8 | 28 => Some(28),
+        9 | 29 => Some(29),
+        30..=39 | 90..=97 => Some(39),
+        40..=49 | 100..=107 => Some(49),
+        53 | 55 => Some(55),
+        _ => None,
+    };
+
+    let mut rest = line;
+    while let Some(start) = rest.find('\u{001B}') {
+        let len = ansi_sequence_len(&rest[start..]);
+        let escape = &rest[start..start + len];
+        rest = &rest[start + len..];
+        if !is_sgr(escape) {
+            continue;
+        }
+
+        let mut params = escape[2..escape.len() - 1].split(';');
+        while let Some(param) = params.next() {
+            let code = match param {
+                // Extended colors carry their arguments along
+                "38" | "48" => match params.next() {
+                    Some("5") => format!("{};5;{}", param, params.next().unwrap_or("0")),
+                    Some("2") => {
+                        let rgb: Vec<&str> = params.by_ref().take(3).collect();
+                        format!("{};2;{}", param, rgb.join(";"))
+                    }
+                    _ => continue,
+                },
+                "" | "0" => {
+                    active.clear();
+                    continue;
+                }
+                _ => param.to_string(),
+            };
+            match group(&code) {
+                Some(off) => {
+                    active.retain(|other| group(other) != Some(off));
+                    if code.parse::<u16>() != Ok(off) {
+                        active.push(code);
+                    }
+                }
+                None => active.push(code),
+            }
+        }
+    }
+}
+
+/// The escape sequence that restores `active` parameters from
+/// [`track_sgr`], or nothing when none are in effect
+fn reopen_sgr(active: &[String]) -> String {
+    match active.is_empty() {
+        true => String::new(),
+        false => format!("\u{001B}[{}m", active.join(";")),
+    }
+}
+
+/// Accumulates wrapped rows, carrying open SGR state across row breaks so
+/// every row opens and closes its own colors.
+struct Wrapper<'a> {
+    width: usize,
+    rows: Vec<String>,
+    row: String,
+    row_width: usize,
+    active: Vec<&'a str>,
+}
+
+impl<'a> Wrapper<'a> {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            rows: Vec::new(),
+            row: String::new(),
+            row_width: 0,
+            active: Vec::new(),
+        }
+    }
+
+    fn push_escape(&mut self, escape: &'a str) {
+        if is_sgr(escape) {
+            if matches!(escape, "\u{001B}[0m" | "\u{001B}[m") {
+                self.active.clear();
+            } else {
+                self.active.push(escape);
+            }
+        }
+        self.row.push_str(escape);
+    }
+
+    fn break_row(&mut self) {
+        let mut row = std::mem::replace(&mut self.row, self.active.concat());
+        if !self.active.is_empty() {
+            row.push_str(SGR_RESET);
+        }
+        self.rows.push(row);
+        self.row_width = 0;
+    }
+
+    /// Place a word, moving it to a fresh row if it doesn't fit after
+    /// `spaces` separating spaces and breaking it between graphemes if it is
+    /// wider than a whole row.
+    fn push_word(&mut self, word: &[Piece<'a>], word_width: usize, spaces: usize) {
+        if self.row_width > 0 && self.row_width + spaces + word_width > self.width {
+            self.break_row();
+        } else {
+            let spaces = spaces.min(self.width - self.row_width);
+            self.row.push_str(&" ".repeat(spaces));
+            self.row_width += spaces;
+        }
+        for piece in word {
+            match *piece {
+                Piece::Escape(escape) => self.push_escape(escape),
+                Piece::Grapheme(grapheme, width) => {
+                    // A wide grapheme that would straddle the right edge moves
+                    // down whole; the column it leaves is padded when the row
+                    // is laid out
+                    if self.row_width > 0 && self.row_width + width > self.width {
+                        self.break_row();
+                    }
+                    self.row.push_str(grapheme);
+                    self.row_width += width;
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.rows.push(self.row);
+        self.rows
+    }
+}
+
+/// Word-wrap `line` to rows at most `width` columns wide.
+///
+/// Breaks fall on spaces, which are dropped at the break; a word wider than
+/// `width` is split between graphemes. SGR state open at a break is reset at
+/// the end of the row and reopened at the start of the next.
+fn wrap_line(line: &str, width: usize, strategy: &dyn WidthStrategy) -> Vec<String> {
+    if width == 0 || strategy.width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    let mut wrapper = Wrapper::new(width);
+    let mut word: Vec<Piece> = Vec::new();
+    let mut word_width = 0;
+    let mut spaces = 0;
+    for piece in pieces(line, strategy) {
+        match piece {
+            Piece::Grapheme(" ", _) => {
+                if !word.is_empty() {
+                    wrapper.push_word(&word, word_width, spaces);
+                    word.clear();
+                    word_width = 0;
+                    spaces = 0;
+                }
+                spaces += 1;
+            }
+            Piece::Grapheme(_, width) => {
+                word_width += width;
+                word.push(piece);
+            }
+            Piece::Escape(_) => word.push(piece),
+        }
+    }
+    if word.is_empty() {
+        // Trailing spaces are kept only while they fit
+        let spaces = spaces.min(width.saturating_sub(wrapper.row_width));
+        wrapper.push_word(&word, 0, spaces);
+    } else {
+        wrapper.push_word(&word, word_width, spaces);
+    }
+    wrapper.finish()
+}
+
+/// Break `line` into rows at most `width` columns wide, filling each row
+/// before starting the next so every space is kept.
+fn break_line(line: &str, width: usize, strategy: &dyn WidthStrategy) -> Vec<String> {
+    if width == 0 || strategy.width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    let mut wrapper = Wrapper::new(width);
+    for piece in pieces(line, strategy) {
+        match piece {
+            Piece::Escape(escape) => wrapper.push_escape(escape),
+            Piece::Grapheme(_, width) => wrapper.push_word(&[piece], width, 0),
+        }
+    }
+    wrapper.finish()
+}
+
+/// Build a horizontal rule exactly `width` columns wide out of `ch`.
+///
+/// Wide characters are repeated as many times as fit and any leftover
+/// column is filled with a space, so the result is always `width` wide.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::rule;
+///
+/// assert_eq!(rule(3, '─'), "───");
+/// assert_eq!(rule(5, '古'), "古古 ");
+/// ```
+pub fn rule(width: usize, ch: char) -> String {
+    let ch_width = string_width(ch.encode_utf8(&mut [0; 4])).max(1);
+    let count = width / ch_width;
+    let mut out = ch.to_string().repeat(count);
+    out.push_str(&" ".repeat(width - count * ch_width));
+    out
+}
+
+/// Stack rendered blocks vertically, one under the next
+///
+/// Empty blocks, such as a box hidden by `hide_if_empty`, are skipped so
+/// they don't leave blank rows behind.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen, vcat};
+///
+/// let stacked = vcat(&[boxen("a", None), String::new(), boxen("b", None)]);
+/// assert_eq!(stacked, "┌─┐\n│a│\n└─┘\n┌─┐\n│b│\n└─┘");
+/// ```
+pub fn vcat<S: AsRef<str>>(blocks: &[S]) -> String {
+    let blocks: Vec<&str> = blocks
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|block| !block.is_empty())
+        .collect();
+    blocks.join("\n")
+}
+
+/// Heuristically detect whether a string is already a box drawn with one of
+/// the preset border styles.
+///
+/// ANSI styling, blank margin lines and surrounding whitespace are ignored.
+/// The first and last lines must carry matching corner glyphs and every line
+/// in between must start and end with a vertical edge (or separator junction)
+/// of the same style.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen, is_boxed};
+///
+/// assert!(is_boxed(&boxen("hello", None)));
+/// assert!(!is_boxed("hello"));
+/// ```
+pub fn is_boxed(s: &str) -> bool {
+    let stripped: Vec<Cow<'_, str>> = s.lines().map(string_width::strip_ansi).collect();
+    let lines: Vec<&str> = stripped
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.len() < 2 {
+        return false;
+    }
+
+    let first = lines[0];
+    let last = lines[lines.len() - 1];
+    let body = &lines[1..lines.len() - 1];
+
+    BorderStyle::PRESETS.iter().any(|style| {
+        let chars = style.chars();
+        let is_edge = |line: &str| {
+            (line.starts_with(chars.vertical) && line.ends_with(chars.vertical))
+                || (line.starts_with(chars.left_tee) && line.ends_with(chars.right_tee))
+        };
+
+        first.starts_with(chars.top_left)
+            && first.ends_with(chars.top_right)
+            && last.starts_with(chars.bottom_left)
+            && last.ends_with(chars.bottom_right)
+            && body.iter().all(|line| is_edge(line))
+    })
+}
+
+/// Draw a box around text
+///
+/// Empty text still draws a box, with a single empty interior row that
+/// grows with any padding. The text may be a `&str`, `String` or anything
+/// else that borrows as one.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen, BoxenOptions, BorderStyle};
+///
+/// let result = boxen("hello", None);
+/// assert_eq!(result, "┌─────┐\n│hello│\n└─────┘");
+///
+/// let opts = BoxenOptions::new().border_style(BorderStyle::Round);
+/// let result = boxen("hello", Some(opts));
+/// assert_eq!(result, "╭─────╮\n│hello│\n╰─────╯");
+/// ```
+pub fn boxen(text: impl AsRef<str>, opts: Option<BoxenOptions>) -> String {
+    let opts = opts.unwrap_or_default();
+    let mut result = String::new();
+    boxen_into(&mut result, text.as_ref(), &opts);
+    result
+}
+
+/// Draw a box around text, failing if content would overflow it
+///
+/// Under [`Overflow::Error`], any content line wider than the box allows
+/// (the fixed `width`, or `max_width` when no width is set, less borders and
+/// padding) is reported instead of being wrapped. Lines are measured as they
+/// are drawn, after markup and tab expansion. Other overflow modes never
+/// fail.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{try_boxen, BoxenOptions, Overflow};
+///
+/// let opts = BoxenOptions::new().width(8).overflow(Overflow::Error);
+/// assert_eq!(try_boxen("short", Some(opts.clone())).unwrap(), "┌──────┐\n│short │\n└──────┘");
+///
+/// let err = try_boxen("far too long", Some(opts)).unwrap_err();
+/// assert_eq!((err.line, err.width, err.limit), (0, 12, 6));
+/// ```
+pub fn try_boxen(
+    text: impl AsRef<str>,
+    opts: Option<BoxenOptions>,
+) -> Result<String, OverflowError> {
+    let text = text.as_ref();
+    let opts = opts.unwrap_or_default();
+    if opts.overflow == Overflow::Error {
+        let limit = content_cap(&opts);
+        let breaks = |c| is_line_break(c, opts.unicode_line_breaks);
+        let mut open_tags = Vec::new();
+        for (line, content) in text.split(breaks).enumerate() {
+            let width = string_width(&prepare_line(content, &opts, &mut open_tags));
+            if width > limit {
+                return Err(OverflowError { line, width, limit });
+            }
+        }
+    }
+    Ok(boxen(text, Some(opts)))
+}
+
+/// Draw a box around text, appending it to an existing buffer
+///
+/// Nothing is added before or after the box, so callers composing several
+/// boxes choose their own separators.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_into, BoxenOptions};
+///
+/// let mut buf = String::from("> ");
+/// boxen_into(&mut buf, "hi", &BoxenOptions::new());
+/// assert_eq!(buf, "> ┌──┐\n│hi│\n└──┘");
+/// ```
+pub fn boxen_into(buf: &mut String, text: &str, opts: &BoxenOptions) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    render_unicode(buf, &lines, opts);
+}
+
+/// Draw a box around text, measuring content with a custom width strategy
+///
+/// Border glyphs and padding are still laid out one column per cell, so the
+/// strategy decides how much room each content line claims.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen, boxen_with, BoxenOptions};
+/// use string_width::UnicodeWidth;
+///
+/// let opts = BoxenOptions::new();
+/// assert_eq!(boxen_with("hi", &opts, &UnicodeWidth), boxen("hi", Some(opts)));
+/// ```
+pub fn boxen_with<W: WidthStrategy>(text: &str, opts: &BoxenOptions, strategy: &W) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut result = String::new();
+    render_into(&mut result, &lines, opts, strategy);
+    result
+}
+
+/// A rendered box along with its geometry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderedBox {
+    /// The output rows, without line terminators
+    pub lines: Vec<String>,
+    /// The width of every row, in columns
+    pub width: usize,
+    /// The number of rows
+    pub height: usize,
+    /// Where content is drawn inside the border and padding, as
+    /// `(row_start, col_start, rows, cols)` in output coordinates
+    pub content_region: (usize, usize, usize, usize),
+}
+
+/// Text attributes of a [`Cell`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attrs {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+/// One terminal cell of a box drawn by [`boxen_grid`]
+///
+/// A wide character takes its own cell followed by a continuation cell
+/// holding [`Cell::CONTINUATION`], with the same colors, so every row has one
+/// cell per column. Combining marks and joined sequences keep only their
+/// first `char`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attrs: Attrs,
+}
+
+impl Cell {
+    /// The `ch` of the cell covering the second column of a wide character
+    pub const CONTINUATION: char = '\0';
+
+    /// Whether this cell only marks the second column of a wide character
+    pub fn is_continuation(&self) -> bool {
+        self.ch == Cell::CONTINUATION
+    }
+
+    /// Update the colors and attributes from the parameters of one SGR
+    /// escape sequence
+    fn apply_sgr(&mut self, escape: &str) {
+        // An empty parameter means 0, but one that doesn't parse is skipped
+        // rather than read as a reset
+        let params: Vec<Option<u32>> = escape[2..escape.len() - 1]
+            .split(';')
+            .map(|param| match param {
+                "" => Some(0),
+                _ => param.parse().ok(),
+            })
+            .collect();
+        let byte = |index: usize| {
+            params
+                .get(index)
+                .copied()
+                .flatten()
+                .and_then(|n| u8::try_from(n).ok())
+        };
+        let mut index = 0;
+        while index < params.len() {
+            let param = params[index];
+            index += 1;
+            let Some(param) = param else {
+                continue;
+            };
+            match param {
+                0 => {
+                    self.fg = None;
+                    self.bg = None;
+                    self.attrs = Attrs::default();
+                }
+                1 => self.attrs.bold = true,
+                2 => self.attrs.dim = true,
+                3 => self.attrs.italic = true,
+                4 => self.attrs.underline = true,
+                7 => self.attrs.reverse = true,
+                22 => (self.attrs.bold, self.attrs.dim) = (false, false),
+                23 => self.attrs.italic = false,
+                24 => self.attrs.underline = false,
+                27 => self.attrs.reverse = false,
+                30..=37 => {
+                    self.fg = Some(Color::Named(NamedColor::from_index(
+                        param as u8 - 30,
+                        false,
+                    )))
+                }
+                90..=97 => {
+                    self.fg = Some(Color::Named(NamedColor::from_index(param as u8 - 90, true)))
+                }
+                40..=47 => {
+                    self.bg = Some(Color::Named(NamedColor::from_index(
+                        param as u8 - 40,
+                        false,
+                    )))
+                }
+                100..=107 => {
+                    self.bg = Some(Color::Named(NamedColor::from_index(
+                        param as u8 - 100,
+                        true,
+                    )))
+                }
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 | 48 => {
+                    let color = match params.get(index) {
+                        Some(Some(5)) => {
+                            index += 2;
+                            byte(index - 1).map(Color::Ansi256)
+                        }
+                        Some(Some(2)) => {
+                            index += 4;
+                            match (byte(index - 3), byte(index - 2), byte(index - 1)) {
+                                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    // A color that can't be read leaves the current one
+                    match (param, color) {
+                        (_, None) => {}
+                        (38, color) => self.fg = color,
+                        (_, color) => self.bg = color,
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            fg: None,
+            bg: None,
+            attrs: Attrs::default(),
+        }
+    }
+}
+
+/// Draw a box around text and report where everything landed
+///
+/// Useful for TUIs that need to place a cursor inside the box. Content that
+/// `hide_if_empty` suppresses yields an empty `RenderedBox`.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_rendered, BoxenOptions, Spacing};
+///
+/// let rendered = boxen_rendered("hi", &BoxenOptions::new().padding(Spacing::uniform(1)));
+/// assert_eq!((rendered.width, rendered.height), (6, 5));
+/// assert_eq!(rendered.content_region, (2, 2, 1, 2));
+/// ```
+pub fn boxen_rendered(text: &str, opts: &BoxenOptions) -> RenderedBox {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut output = String::new();
+    let mut rendered = render_unicode(&mut output, &lines, opts);
+    if !output.is_empty() {
+        rendered.lines = output.split('\n').map(str::to_string).collect();
+    }
+    rendered
+}
+
+/// Draw a box around text as a grid of cells instead of a string
+///
+/// Each row holds one [`Cell`] per column, with the colors and attributes
+/// the ANSI output would give it, so a TUI can composite the box into its
+/// own buffer without parsing escape sequences back out.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_grid, BoxenOptions, Color, NamedColor};
+///
+/// let red = Color::Named(NamedColor::Red);
+/// let grid = boxen_grid("hi", &BoxenOptions::new().border_color(red));
+/// assert_eq!((grid.len(), grid[0].len()), (3, 4));
+/// assert_eq!((grid[0][0].ch, grid[0][0].fg), ('┌', Some(red)));
+/// assert_eq!((grid[1][1].ch, grid[1][1].fg), ('h', None));
+/// ```
+pub fn boxen_grid(text: &str, opts: &BoxenOptions) -> Vec<Vec<Cell>> {
+    let output = boxen(text, Some(opts.clone()));
+    if output.is_empty() {
+        return Vec::new();
+    }
+
+    output
+        .split('\n')
+        .map(|row| {
+            let mut pen = Cell::default();
+            let mut cells = Vec::new();
+            for piece in pieces(row, &UnicodeWidth) {
+                match piece {
+                    Piece::Escape(escape) if is_sgr(escape) => pen.apply_sgr(escape),
+                    Piece::Escape(_) | Piece::Grapheme(_, 0) => {}
+                    Piece::Grapheme(grapheme, width) => {
+                        let ch = grapheme.chars().next().unwrap_or(' ');
+                        cells.push(Cell { ch, ..pen });
+                        cells.extend(std::iter::repeat_n(
+                            Cell {
+                                ch: Cell::CONTINUATION,
+                                ..pen
+                            },
+                            width - 1,
+                        ));
+                    }
+                }
+            }
+            cells
+        })
+        .collect()
+}
+
+/// Draw a box around text using a ready-made theme
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_themed, Theme};
+///
+/// let result = boxen_themed("disk almost full", Theme::Warning);
+/// assert!(result.contains("⚠ Warning"));
+/// ```
+pub fn boxen_themed(text: &str, theme: Theme) -> String {
+    boxen(text, Some(theme.options()))
+}
+
+/// Draw a box around content that has already been split into lines
+///
+/// Each entry becomes one content row. An entry that itself contains `\n`
+/// is split further, so the result always matches `boxen` on the joined text.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen, boxen_lines, BoxenOptions};
+///
+/// let opts = BoxenOptions::new();
+/// assert_eq!(boxen_lines(&["a", "bb"], &opts), boxen("a\nbb", Some(opts)));
+/// ```
+pub fn boxen_lines(lines: &[&str], opts: &BoxenOptions) -> String {
+    let mut result = String::new();
+    render_unicode(&mut result, lines, opts);
+    result
+}
+
+/// Compare two rendered boxes, describing the first difference
+///
+/// Rows are compared with ANSI escape sequences stripped, so only what is
+/// visible counts. Returns `None` when the boxes look identical, and
+/// otherwise the first differing row and display column (both from 1) with
+/// the two rows side by side. Requires the `testing` feature.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "testing")]
+/// # {
+/// use boxen::{box_diff, boxen};
+///
+/// assert_eq!(box_diff(&boxen("hi", None), &boxen("hi", None)), None);
+/// assert_eq!(
+///     box_diff(&boxen("hi", None), &boxen("ho", None)).unwrap(),
+///     "row 2, column 3 differs\n  left:  │hi│\n  right: │ho│"
+/// );
+/// # }
+/// ```
+#[cfg(any(test, feature = "testing"))]
+pub fn box_diff(a: &str, b: &str) -> Option<String> {
+    let a = string_width::strip_ansi(a);
+    let b = string_width::strip_ansi(b);
+    let mut left = a.split('\n');
+    let mut right = b.split('\n');
+    for row in 1.. {
+        match (left.next(), right.next()) {
+            (None, None) => return None,
+            (Some(left), Some(right)) if left == right => {}
+            (Some(left), Some(right)) => {
+                let same: String = left
+                    .graphemes(true)
+                    .zip(right.graphemes(true))
+                    .take_while(|(l, r)| l == r)
+                    .map(|(l, _)| l)
+                    .collect();
+                return Some(format!(
+                    "row {}, column {} differs\n  left:  {}\n  right: {}",
+                    row,
+                    string_width(&same) + 1,
+                    left,
+                    right
+                ));
+            }
+            (Some(left), None) => {
+                return Some(format!("row {} is only on the left: {}", row, left));
+            }
+            (None, Some(right)) => {
+                return Some(format!("row {} is only on the right: {}", row, right));
+            }
+        }
+    }
+    unreachable!("the row loop only ends by returning")
+}
+
+/// Draw a box around key/value pairs, one per row, with the values lined up
+///
+/// Each key is followed by a colon and padded to the widest key, so every
+/// value starts in the same column. A value spanning several lines keeps
+/// its later lines in that column too.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_kv, BoxenOptions};
+///
+/// let result = boxen_kv(&[("name", "boxen"), ("license", "MIT")], &BoxenOptions::new());
+/// assert_eq!(
+///     result,
+///     "┌──────────────┐\n│name:    boxen│\n│license: MIT  │\n└──────────────┘"
+/// );
+/// ```
+pub fn boxen_kv(pairs: &[(&str, &str)], opts: &BoxenOptions) -> String {
+    let key_width = pairs
+        .iter()
+        .map(|(key, _)| string_width(key))
+        .max()
+        .unwrap_or(0);
+    let indent = " ".repeat(key_width + 2);
+    let rows: Vec<String> = pairs
+        .iter()
+        .flat_map(|(key, value)| {
+            let pad = " ".repeat(key_width - string_width(key));
+            let mut lines = value.split('\n');
+            let first = format!("{}:{} {}", key, pad, lines.next().unwrap_or_default());
+            std::iter::once(first)
+                .chain(lines.map(|line| format!("{}{}", indent, line)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    boxen_lines(&rows, opts)
+}
+
+/// Draw a separate box around each paragraph, stacked with [`vcat`]
+///
+/// Paragraphs are separated by one or more blank (whitespace-only) lines,
+/// which are dropped. Each box is sized to its own paragraph, so use
+/// `width` in `opts` for boxes of equal width; a margin spaces them apart.
+/// Text with no paragraphs at all renders as an empty string.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_paragraphs, BoxenOptions};
+///
+/// let result = boxen_paragraphs("one\n\ntwo", &BoxenOptions::new());
+/// assert_eq!(result, "┌───┐\n│one│\n└───┘\n┌───┐\n│two│\n└───┘");
+/// ```
+pub fn boxen_paragraphs(text: &str, opts: &BoxenOptions) -> String {
+    let mut paragraphs: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.split('\n') {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+
+    let boxes: Vec<String> = paragraphs
+        .iter()
+        .map(|paragraph| boxen_lines(paragraph, opts))
+        .collect();
+    vcat(&boxes)
+}
+
+/// Draw a box around lines as they arrive, yielding one output row at a time
+///
+/// The top border (with any margin and padding above it) comes first, then
+/// the rows for each line as it is pulled from `lines`, then the bottom
+/// border once `lines` is exhausted, so unbounded streams can be framed.
+/// This needs a fixed `width` and the default overflow and orientation;
+/// otherwise sizing the box takes every line, and they are all collected
+/// before the first row is yielded. `hide_if_empty` and `trim_blank_lines`
+/// are ignored while streaming.
+///
+/// # Examples
+///
+/// ```
+/// use boxen::{boxen_iter, BoxenOptions};
+///
+/// let lines = ["a", "b"].into_iter().map(String::from);
+/// let rows: Vec<String> = boxen_iter(lines, &BoxenOptions::new().width(4)).collect();
+/// assert_eq!(rows, ["┌──┐", "│a │", "│b │", "└──┘"]);
+/// ```
+pub fn boxen_iter<I: Iterator<Item = String>>(
+    lines: I,
+    opts: &BoxenOptions,
+) -> impl Iterator<Item = String> {
+    let opts = opts.clone();
+    let streaming = opts.width.is_some()
+        && opts.overflow == Overflow::Visible
+        && opts.orientation == Orientation::Horizontal;
+    // Each line is drawn as a box of its own, without the rows above and
+    // below its content, so every row comes out exactly as in the full box
+    let line_opts = BoxenOptions {
+        padding: Spacing {
+            top: 0,
+            bottom: 0,
+            ..opts.padding
+        },
+        margin: Spacing {
+            top: 0,
+            bottom: 0,
+            ..opts.margin
+        },
+        separators: Vec::new(),
+        hide_if_empty: false,
+        trim_blank_lines: false,
+        ..opts.clone()
+    };
+    let content_rows = move |line: &str, first_row: usize| -> Vec<String> {
+        let mut row_opts = line_opts.clone();
+        // Keep the zebra stripes alternating across lines
+        if first_row % 2 == 1 {
+            row_opts.zebra = row_opts.zebra.map(|(even, odd)| (odd, even));
+        }
+        let mut buf = String::new();
+        let (start, _, count, _) = render_unicode(&mut buf, &[line], &row_opts).content_region;
+        buf.split('\n')
+            .skip(start)
+            .take(count)
+            .map(str::to_string)
+            .collect()
+    };
+
+    let mut lines = lines.fuse();
+    let mut pending: VecDeque<String> = VecDeque::new();
+    let mut tail: Vec<String> = Vec::new();
+    let mut separator = String::new();
+    let mut started = false;
+    let mut finished = false;
+    let mut rows_drawn = 0;
+    let mut active: Vec<String> = Vec::new();
+    std::iter::from_fn(move || {
+        loop {
+            if let Some(row) = pending.pop_front() {
+                return Some(row);
+            }
+            if finished {
+                return None;
+            }
+
+            if !streaming {
+                let lines: Vec<String> = lines.by_ref().collect();
+                let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                pending.extend(boxen_lines(&lines, &opts).split('\n').map(str::to_string));
+                finished = true;
+                continue;
+            }
+
+            if !started {
+                started = true;
+                let frame_opts = BoxenOptions {
+                    separators: Vec::new(),
+                    hide_if_empty: false,
+                    ..opts.clone()
+                };
+                let mut frame = String::new();
+                let (start, ..) = render_unicode(&mut frame, &[""], &frame_opts).content_region;
+                let frame: Vec<&str> = frame.split('\n').collect();
+                pending.extend(frame[..start].iter().map(|row| row.to_string()));
+                tail = frame[start + 1..]
+                    .iter()
+                    .map(|row| row.to_string())
+                    .collect();
+
+                let separated = BoxenOptions {
+                    separators: vec![0],
+                    ..frame_opts
+                };
+                let mut buf = String::new();
+                let (start, ..) = render_unicode(&mut buf, &["", ""], &separated).content_region;
+                separator = buf
+                    .split('\n')
+                    .nth(start + 1)
+                    .unwrap_or_default()
+                    .to_string();
+                continue;
+            }
+
+            let line = match lines.next() {
+                Some(line) => line,
+                // No lines at all still draws one empty row, as `boxen("")`
+                None if rows_drawn == 0 => String::new(),
+                None => {
+                    pending.extend(tail.drain(..));
+                    finished = true;
+                    continue;
+                }
+            };
+            // Carry styles left open by earlier lines, as the full box does
+            let reopen = reopen_sgr(&active);
+            track_sgr(&mut active, &line);
+            for row in content_rows(&(reopen + &line), rows_drawn) {
+                pending.push_back(row);
+                if opts.separators.contains(&rows_drawn) {
+                    pending.push_back(separator.clone());
+                }
+                rows_drawn += 1;
+            }
+        }
+    })
+}
+
+/// Render with the default width strategy, under which every output row
+/// is guaranteed to be the same width, apart from a first row shortened by
+/// `initial_column`
+fn render_unicode(buf: &mut String, lines: &[&str], opts: &BoxenOptions) -> RenderedBox {
+    let start = buf.len();
+    let rendered = render_into(buf, lines, opts, &UnicodeWidth);
+    debug_assert!(
+        opts.debug || {
+            // The first row is short by `initial_column` on purpose
+            let widths: Vec<usize> = buf[start..]
+                .split('\n')
+                .skip(usize::from(opts.initial_column > 0))
+                .map(string_width)
+                .collect();
+            widths.windows(2).all(|pair| pair[0] == pair[1])
+        },
+        "boxen rendered a ragged box"
+    );
+    rendered
+}
+
+/// The widest the box may grow and the fixed width it is drawn at, if any,
+/// both borders included
+fn width_limits(opts: &BoxenOptions) -> (usize, Option<usize>) {
+    let columns = opts.terminal_width.unwrap_or_else(fallback_columns);
+    let margin = resolved_margin(opts, columns);
+    // Without an explicit cap the box may use whatever the margins leave of
+    // the terminal
+    let max_width = opts
+        .max_width
+        .unwrap_or_else(|| columns.saturating_sub(margin.left + margin.right));
+    let fixed_width = opts
+        .width
+        .map(|width| width.resolve(columns).min(max_width));
+    (max_width, fixed_width)
+}
+
+/// The margin with `margin_x` resolved against a terminal `columns` wide
+fn resolved_margin(opts: &BoxenOptions, columns: usize) -> Spacing {
+    match opts.margin_x {
+        Some(margin_x) => {
+            let cells = margin_x.resolve(columns);
+            Spacing {
+                left: cells,
+                right: cells,
+                ..opts.margin
+            }
+        }
+        None => opts.margin,
+    }
+}
+
+/// The widest a content line may be before it overflows the box
+fn content_cap(opts: &BoxenOptions) -> usize {
+    let (max_width, fixed_width) = width_limits(opts);
+    fixed_width
+        .unwrap_or(max_width)
+        .saturating_sub(2 * opts.border_width.max(1) + opts.padding.left + opts.padding.right)
+}
+
+/// A content line as it is drawn: markup applied, with tags left open
+/// carried in `open_tags` to the next line, and tabs expanded
+fn prepare_line<'a>(
+    line: &'a str,
+    opts: &BoxenOptions,
+    open_tags: &mut Vec<(u8, u8)>,
+) -> Cow<'a, str> {
+    let line = match opts.markup && !opts.code_block && line.contains('[') {
+        true => Cow::Owned(apply_markup(line, open_tags)),
+        false => Cow::Borrowed(line),
+    };
+    // Each line starts at the interior's left edge, so its tab stops are
+    // counted from there rather than from the terminal
+    match opts.tab_width > 0 && line.contains('\t') {
+        true => Cow::Owned(expand_tabs(&line, opts.tab_width)),
+        false => line,
+    }
+}
+
+fn render_into(
+    buf: &mut String,
+    lines: &[&str],
+    opts: &BoxenOptions,
+    strategy: &dyn WidthStrategy,
+) -> RenderedBox {
+    if opts.hide_if_empty && lines.iter().all(|line| line.trim().is_empty()) {
+        return RenderedBox::default();
+    }
+
+    let piped = opts.ascii_when_piped
+        && !opts
+            .is_tty
+            .unwrap_or_else(|| std::io::stdout().is_terminal());
+    let mut chars = match piped {
+        true => BorderStyle::Ascii,
+        false => opts.border_style.unwrap_or_else(fallback_border_style),
+    }
+    .chars();
+    // Corner shapes always start from the style's own corners
+    let style_chars = chars;
+    if let Some(corner_style) = opts.corner_style.filter(|_| !piped) {
+        chars = corner_style.apply(style_chars);
+    }
+    if let Some(quad) = opts.corner_styles.filter(|_| !piped) {
+        chars = BorderChars {
+            top_left: quad.top_left.apply(style_chars).top_left,
+            top_right: quad.top_right.apply(style_chars).top_right,
+            bottom_left: quad.bottom_left.apply(style_chars).bottom_left,
+            bottom_right: quad.bottom_right.apply(style_chars).bottom_right,
+            ..chars
+        };
+    }
+    if opts.corner_fill && !piped {
+        chars = BorderChars {
+            top_left: "▛",
+            top_right: "▜",
+            bottom_left: "▙",
+            bottom_right: "▟",
+            ..chars
+        };
+    }
+    // Right-to-left boxes mirror every horizontal choice
+    let mirror_spacing = |spacing: Spacing| match opts.rtl {
+        true => Spacing {
+            left: spacing.right,
+            right: spacing.left,
+            ..spacing
+        },
+        false => spacing,
+    };
+    let mirror_alignment = |alignment: Alignment| match (opts.rtl, alignment) {
+        (true, Alignment::Left) => Alignment::Right,
+        (true, Alignment::Right) => Alignment::Left,
+        (_, alignment) => alignment,
+    };
+    let columns = opts.terminal_width.unwrap_or_else(fallback_columns);
+    let padding = mirror_spacing(opts.padding);
+    let margin = mirror_spacing(resolved_margin(opts, columns));
+    let border = opts.border_width.max(1);
+
+    // No lines at all renders like one empty line, the same as `boxen("")`
+    let lines = if lines.is_empty() { &[""][..] } else { lines };
+    // Tags stay open from one line to the next until closed
+    let mut open_tags = Vec::new();
+    let mut lines: Vec<(Cow<str>, usize)> = lines
+        .iter()
+        .flat_map(|line| line.split(|c| is_line_break(c, opts.unicode_line_breaks)))
+        .map(|line| {
+            let line = prepare_line(line, opts, &mut open_tags);
+            let width = strategy.width(&line);
+            (line, width)
+        })
+        .collect();
+    if opts.orientation != Orientation::Horizontal {
+        lines = rotate(&lines, opts.orientation, strategy)
+            .into_iter()
+            .map(|row| {
+                let row_width = strategy.width(&row);
+                (Cow::Owned(row), row_width)
+            })
+            .collect();
+    }
+    if opts.trim_blank_lines && !opts.code_block {
+        let is_blank = |(line, _): &(Cow<str>, usize)| line.trim().is_empty();
+        let end = lines
+            .iter()
+            .rposition(|line| !is_blank(line))
+            .map_or(0, |i| i + 1);
+        lines.truncate(end);
+        let start = lines.iter().position(|line| !is_blank(line)).unwrap_or(end);
+        lines.drain(..start);
+        if lines.is_empty() {
+            lines.push((Cow::Borrowed(""), 0));
+        }
+    }
+    let (_, fixed_width) = width_limits(opts);
+    if opts.overflow == Overflow::Visible {
+        let cap = content_cap(opts);
+        lines = lines
+            .into_iter()
+            .flat_map(|(line, line_width)| {
+                if line_width <= cap {
+                    return vec![(line, line_width)];
+                }
+                let rows = match opts.code_block {
+                    true => break_line(&line, cap, strategy),
+                    false => wrap_line(&line, cap, strategy),
+                };
+                rows.into_iter()
+                    .map(|row| {
+                        let row_width = strategy.width(&row);
+                        (Cow::Owned(row), row_width)
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+    // Borders and padding come between one line and the next, so a style
+    // left open at the end of a line is closed there and reopened after
+    let mut active: Vec<String> = Vec::new();
+    for (line, _) in lines.iter_mut() {
+        let reopen = reopen_sgr(&active);
+        track_sgr(&mut active, line);
+        if !reopen.is_empty() || !active.is_empty() {
+            let close = if active.is_empty() { "" } else { SGR_RESET };
+            *line = Cow::Owned(format!("{}{}{}", reopen, line, close));
+        }
+    }
+    let mut content_width = lines.iter().map(|(_, width)| *width).max().unwrap_or(0);
+    let measured: Vec<usize> = lines.iter().map(|(_, width)| *width).collect();
+
+    let frame_width = margin.left + margin.right + 2 * border + padding.left + padding.right;
+    if opts.overflow == Overflow::Scroll && frame_width + content_width > columns {
+        // One column on each side of the window is reserved for the markers,
+        // which are dropped when even they don't fit
+        let room = columns.saturating_sub(frame_width);
+        let markers = room >= 2;
+        let window = if markers { room - 2 } else { room };
+        for (line, width) in lines.iter_mut() {
+            let visible = clip_columns(line, opts.scroll_x, window, strategy);
+            if markers {
+                let before = if opts.scroll_x > 0 && *width > 0 {
+                    "◀"
+                } else {
+                    " "
+                };
+                let after = if *width > opts.scroll_x + window {
+                    "▶"
+                } else {
+                    " "
+                };
+                *line = Cow::Owned(format!("{}{}{}", before, visible, after));
+            } else {
+                *line = Cow::Owned(visible);
+            }
+            *width = room;
+        }
+        content_width = room;
+    }
+
+    let title = opts
+        .title
+        .as_ref()
+        .map(|title| format!(" {} ", title.replace('\n', " ")));
+    let title_width = title
+        .as_deref()
+        .map(|title| strategy.width(title))
+        .unwrap_or(0);
+    let mut inner_width = (content_width + padding.left + padding.right)
+        .max(title_width)
+        .max(fixed_width.map_or(0, |width| width.saturating_sub(2 * border)));
+    if opts.even_width && inner_width % 2 == 1 {
+        inner_width += 1;
+    }
+    let align_width = if opts.content_follows_title_width || opts.width.is_some() {
+        inner_width - padding.left - padding.right
+    } else {
+        content_width
+    };
+
+    let grid_columns: Vec<usize> = opts
+        .grid
+        .as_ref()
+        .map(|grid| {
+            let mut columns: Vec<usize> = grid
+                .columns
+                .iter()
+                .copied()
+                .filter(|&column| column < inner_width)
+                .map(|column| match opts.rtl {
+                    true => inner_width - 1 - column,
+                    false => column,
+                })
+                .collect();
+            columns.sort_unstable();
+            columns.dedup();
+            columns
+        })
+        .unwrap_or_default();
+
+    let paint_in = |color: Option<Color>, glyphs: &str| match color {
+        Some(color) => format!("{}{}{}", color.fg(), glyphs, FG_RESET),
+        None => glyphs.to_string(),
+    };
+    let paint = |glyphs: &str| paint_in(opts.border_color, glyphs);
+    let edge = |pick: fn(&EdgeColors) -> Color| {
+        opts.border_colors.as_ref().map(pick).or(opts.border_color)
+    };
+    let (top_color, right_color) = (edge(|edges| edges.top), edge(|edges| edges.right));
+    let (bottom_color, left_color) = (edge(|edges| edges.bottom), edge(|edges| edges.left));
+    // Paints each glyph run in its color, joining neighbours that share one
+    let paint_runs = |runs: &[(Option<Color>, &str)]| {
+        let mut out = String::new();
+        let mut start = 0;
+        for end in 1..=runs.len() {
+            if end == runs.len() || runs[end].0 != runs[start].0 {
+                let glyphs: String = runs[start..end].iter().map(|(_, glyphs)| *glyphs).collect();
+                out.push_str(&paint_in(runs[start].0, &glyphs));
+                start = end;
+            }
+        }
+        out
+    };
+    // A horizontal run of border cells, with `junction` where grid lines meet it
+    let run = |junction: &'static str| -> Vec<&'static str> {
+        let mut cells = vec![chars.horizontal; inner_width];
+        for &column in &grid_columns {
+            cells[column] = junction;
+        }
+        cells
+    };
+    let edge_row = |left: &str, right: &str, junction: &'static str, color: Option<Color>| {
+        let glyphs = format!("{}{}{}", left, run(junction).concat(), right);
+        paint_in(color, &glyphs)
+    };
+    let vertical = paint(chars.vertical);
+    let left_vertical = paint_in(left_color, chars.vertical);
+    let right_vertical = paint_in(right_color, chars.vertical);
+    let filled = opts
+        .fill_ratio
+        .map(|ratio| (ratio.clamp(0.0, 1.0) * inner_width as f32).round() as usize);
+    let content_row = |inner: &str, background: Option<Color>| {
+        let mut inner = grid_columns.iter().fold(inner.to_string(), |row, &column| {
+            overlay_cell(&row, column, &vertical, strategy)
+        });
+        if let Some(filled) = filled.filter(|&filled| filled > 0) {
+            let (head, tail) = split_at_column(&inner, filled, strategy);
+            let (on, off, off_code) = match opts.fill_color {
+                Some(color) => (color.bg(), BG_RESET, "49"),
+                None => (REVERSE.to_string(), REVERSE_OFF, "27"),
+            };
+            inner = format!(
+                "{}{}{}{}",
+                on,
+                reassert_sgr(&head, &on, off_code),
+                off,
+                tail
+            );
+        }
+        // The background wraps only the interior, so the border glyphs either
+        // side are always outside the bg-on/bg-off pair; resets inside the
+        // content turn it straight back on
+        match background {
+            Some(color) => format!(
+                "{}{}{}{}{}",
+                left_vertical,
+                color.bg(),
+                reassert_sgr(&inner, &color.bg(), "49"),
+                BG_RESET,
+                right_vertical
+            ),
+            None => format!("{}{}{}", left_vertical, inner, right_vertical),
+        }
+    };
+    let blank_row = content_row(
+        &" ".repeat(inner_width),
+        opts.zebra
+            .map(|(primary, _)| primary)
+            .or(opts.background_color),
+    );
+
+    let mut rows: Vec<String> = Vec::new();
+    // Row index and measured width of every content row, for debug output
+    let mut content_rows: Vec<(usize, usize)> = Vec::new();
+    match &title {
+        Some(title) => {
+            let gap = inner_width - title_width;
+            let lead = match mirror_alignment(opts.title_alignment.resolve(title)) {
+                Alignment::Center => gap / 2,
+                Alignment::Right => gap,
+                _ => 0,
+            };
+            let cells = run(chars.top_tee);
+            let before = format!("{}{}", chars.top_left, cells[..lead].concat());
+            let after = format!(
+                "{}{}",
+                cells[lead + title_width..].concat(),
+                chars.top_right
+            );
+            match opts.title_color {
+                // The title is painted on its own so the dashes either side
+                // keep the border color
+                Some(color) => rows.push(format!(
+                    "{}{}{}{}{}",
+                    paint_in(top_color, &before),
+                    color.fg(),
+                    title,
+                    FG_RESET,
+                    paint_in(top_color, &after)
+                )),
+                None => rows.push(paint_in(
+                    top_color,
+                    &format!("{}{}{}", before, title, after),
+                )),
+            }
+        }
+        None => rows.push(edge_row(
+            chars.top_left,
+            chars.top_right,
+            chars.top_tee,
+            top_color,
+        )),
+    }
+    rows.extend(std::iter::repeat_n(blank_row.clone(), padding.top));
+
+    for (index, (line, width)) in lines.iter().enumerate() {
+        let gap = align_width - width;
+        let alignment = match opts.code_block {
+            true => Alignment::Left,
+            false => mirror_alignment(opts.text_alignment.resolve(line)),
+        };
+        let lead = match alignment {
+            Alignment::Center => gap / 2,
+            Alignment::Right => gap,
+            _ => 0,
+        };
+        let background = opts
+            .zebra
+            .map(|(even, odd)| if index % 2 == 0 { even } else { odd })
+            .or(opts.background_color);
+        content_rows.push((rows.len(), measured[index]));
+        rows.push(content_row(
+            &format!(
+                "{}{}{}",
+                " ".repeat(padding.left + lead),
+                line,
+                " ".repeat(inner_width - padding.left - lead - width)
+            ),
+            background,
+        ));
+
+        if opts.separators.contains(&index) {
+            let run = run(chars.cross).concat();
+            rows.push(paint_runs(&[
+                (left_color, chars.left_tee),
+                (opts.border_color, &run),
+                (right_color, chars.right_tee),
+            ]));
+        }
+    }
+
+    rows.extend(std::iter::repeat_n(blank_row, padding.bottom));
+    rows.push(edge_row(
+        chars.bottom_left,
+        chars.bottom_right,
+        chars.bottom_tee,
+        bottom_color,
+    ));
+
+    // Thicker borders wrap the box in further plain rings of the same style
+    for ring in 1..border {
+        let ring_width = inner_width + 2 * ring;
+        let horizontal = chars.horizontal.repeat(ring_width);
+        let top = paint_in(
+            top_color,
+            &format!("{}{}{}", chars.top_left, horizontal, chars.top_right),
+        );
+        let bottom = paint_in(
+            bottom_color,
+            &format!("{}{}{}", chars.bottom_left, horizontal, chars.bottom_right),
+        );
+        rows = std::iter::once(top)
+            .chain(
+                rows.iter()
+                    .map(|row| format!("{}{}{}", left_vertical, row, right_vertical)),
+            )
+            .chain(std::iter::once(bottom))
+            .collect();
+    }
+    for (row, _) in content_rows.iter_mut() {
+        *row += border - 1;
+    }
+
+    let bleed = opts.bleed.map_or(0, |(cells, _)| cells);
+    if let Some((cells, color)) = opts.bleed.filter(|&(cells, _)| cells > 0) {
+        let mat = |width: usize| format!("{}{}{}", color.bg(), " ".repeat(width), BG_RESET);
+        let full_row = mat(inner_width + 2 * border + 2 * cells);
+        let side = mat(cells);
+        rows = std::iter::repeat_n(full_row.clone(), cells)
+            .chain(rows.iter().map(|row| format!("{}{}{}", side, row, side)))
+            .chain(std::iter::repeat_n(full_row, cells))
+            .collect();
+        for (row, _) in content_rows.iter_mut() {
+            *row += cells;
+        }
+    }
+
+    let box_width = inner_width + 2 * border + 2 * bleed;
+    let caption_rows = match &opts.caption {
+        Some(caption) => {
+            let mut caption = caption.replace('\n', " ");
+            // The strategy may measure wider than `truncate_ansi` does, so
+            // clusters come off one at a time until it agrees the caption fits
+            let mut limit = box_width;
+            while strategy.width(&caption) > box_width && !caption.is_empty() {
+                caption = truncate_ansi(&caption, limit);
+                limit = limit.saturating_sub(1);
+            }
+            let gap = box_width - strategy.width(&caption);
+            let lead = match mirror_alignment(opts.caption_alignment.resolve(&caption)) {
+                Alignment::Center => gap / 2,
+                Alignment::Right => gap,
+                _ => 0,
+            };
+            rows.push(format!(
+                "{}{}{}",
+                " ".repeat(lead),
+                caption,
+                " ".repeat(gap - lead)
+            ));
+            1
+        }
+        None => 0,
+    };
+
+    // Margins are emitted as spaces so every output line has the same width
+    let margin_left = " ".repeat(margin.left);
+    let margin_right = " ".repeat(margin.right);
+    let total_width = margin.left + box_width + margin.right;
+    // `float` and `box_alignment` are two names for one setting
+    let box_alignment = match opts.box_alignment {
+        Alignment::Left => opts.float,
+        alignment => alignment,
+    };
+    let offset = match mirror_alignment(box_alignment) {
+        Alignment::Center => columns.saturating_sub(total_width) / 2,
+        Alignment::Right => columns.saturating_sub(total_width),
+        _ => 0,
+    };
+    let indent = opts.anchor_column.unwrap_or(0) + offset;
+    let anchor = " ".repeat(indent);
+    let blank_margin = " ".repeat(total_width);
+    if opts.debug {
+        let ruler: String = (0..total_width)
+            .map(|column| char::from(b'0' + (column % 10) as u8))
+            .collect();
+        buf.push_str(&ruler);
+        buf.push('\n');
+    }
+    let output = std::iter::repeat_n(blank_margin.as_str(), margin.top)
+        .chain(rows.iter().map(String::as_str))
+        .chain(std::iter::repeat_n(blank_margin.as_str(), margin.bottom));
+    for (index, row) in output.enumerate() {
+        if index > 0 {
+            buf.push('\n');
+        }
+        match index {
+            // The cursor is already `initial_column` along the first row
+            0 => buf.push_str(&anchor[opts.initial_column.min(indent)..]),
+            _ => buf.push_str(&anchor),
+        }
+        if index < margin.top || index >= margin.top + rows.len() {
+            buf.push_str(row);
+        } else {
+            buf.push_str(&margin_left);
+            buf.push_str(row);
+            buf.push_str(&margin_right);
+        }
+        if opts.debug {
+            let row_index = index.wrapping_sub(margin.top);
+            if let Some((_, width)) = content_rows.iter().find(|(row, _)| *row == row_index) {
+                buf.push_str(&format!(" {}", width));
+            }
+        }
+    }
+
+    let ruler = usize::from(opts.debug);
+    RenderedBox {
+        lines: Vec::new(),
+        width: indent + total_width,
+        height: ruler + margin.top + rows.len() + margin.bottom,
+        content_region: (
+            ruler + margin.top + bleed + border + padding.top,
+            indent + margin.left + bleed + border + padding.left,
+            rows.len() - caption_rows - 2 * (bleed + border) - padding.top - padding.bottom,
+            inner_width - padding.left - padding.right,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use string_width::truncate;
+
+    #[test]
+    fn test_default_box() {
+        let result = boxen("hello", None);
+        assert_eq!(result, "┌─────┐\n│hello│\n└─────┘");
+    }
+
+    #[test]
+    fn test_multiline_fills_to_widest() {
+        let result = boxen("hi\nhello", None);
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[1], "│hi   │");
+        assert_eq!(lines[2], "│hello│");
+    }
+
+    #[test]
+    fn test_padding_and_margin() {
+        let opts = BoxenOptions::new()
+            .padding(Spacing {
+                top: 1,
+                right: 1,
+                bottom: 0,
+                left: 1,
+            })
+            .margin(Spacing {
+                top: 1,
+                right: 1,
+                bottom: 0,
+                left: 2,
+            });
+        let result = boxen("hi", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(
+            lines,
+            vec![
+                "         ",
+                "  ┌────┐ ",
+                "  │    │ ",
+                "  │ hi │ ",
+                "  └────┘ "
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_alignment() {
+        let opts = BoxenOptions::new().text_alignment(Alignment::Right);
+        let result = boxen("hi\nhello", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[1], "│   hi│");
+    }
+
+    #[test]
+    fn test_debug_ruler_and_widths() {
+        let opts = BoxenOptions::new()
+            .padding(Spacing::uniform(1))
+            .margin(Spacing {
+                left: 2,
+                ..Spacing::default()
+            });
+        let plain = boxen("ab\n古古古", Some(opts.clone()));
+        let result = boxen("ab\n古古古", Some(opts.debug(true)));
+        let lines: Vec<&str> = result.lines().collect();
+
+        assert_eq!(lines[0], "012345678901");
+        assert_eq!(lines[0].len(), string_width(plain.lines().next().unwrap()));
+        assert_eq!(lines[2], "  │        │");
+        assert_eq!(lines[3], "  │ ab     │ 2");
+        assert_eq!(lines[4], "  │ 古古古 │ 6");
+    }
+
+    #[test]
+    fn test_empty_input_draws_box() {
+        assert_eq!(boxen("", None), "┌┐\n││\n└┘");
+        assert_eq!(boxen_lines(&[], &BoxenOptions::new()), boxen("", None));
+
+        let result = boxen("", Some(BoxenOptions::new().padding(1)));
+        assert_eq!(result, "┌──────┐\n│      │\n│      │\n│      │\n└──────┘");
+    }
+
+    #[test]
+    fn test_initial_column() {
+        let opts = BoxenOptions::new()
+            .terminal_width(20)
+            .box_alignment(Alignment::Center)
+            .initial_column(5);
+        let result = boxen("hi", Some(opts.clone()));
+        let rows: Vec<&str> = result.split('\n').collect();
+        assert_eq!(rows[0], "   ┌──┐");
+        assert_eq!(rows[1], "        │hi│");
+        assert_eq!(rows[2], "        └──┘");
+        assert_eq!(format!("> up {}", rows[0]).find('┌'), rows[1].find('│'));
+
+        // A prompt wider than the indent leaves the first row unindented
+        let result = boxen(
+            "hi",
+            Some(opts.box_alignment(Alignment::Left).anchor_column(2)),
+        );
+        assert_eq!(result, "┌──┐\n  │hi│\n  └──┘");
+    }
+
+    #[test]
+    fn test_anchor_column() {
+        let opts = BoxenOptions::new().margin(Spacing::vertical(1));
+        let plain = boxen("hi", Some(opts.clone()));
+        let result = boxen("hi", Some(opts.anchor_column(5)));
+
+        assert_eq!(result.lines().count(), plain.lines().count());
+        for (anchored, line) in result.lines().zip(plain.lines()) {
+            assert_eq!(anchored, format!("     {}", line));
+        }
+        // The margin-top blank row is emitted and indented too
+        assert_eq!(result.lines().next(), Some(" ".repeat(5 + 4).as_str()));
+    }
+
+    #[test]
+    fn test_fixed_width_wraps_words() {
+        let opts = BoxenOptions::new().width(9);
+        let result = boxen("the quick brown fox", Some(opts));
+        assert_eq!(
+            result,
+            "┌───────┐\n│the    │\n│quick  │\n│brown  │\n│fox    │\n└───────┘"
+        );
+
+        // Narrow content is widened to the fixed width and aligned across it
+        let opts = BoxenOptions::new()
+            .width(8)
+            .text_alignment(Alignment::Right);
+        assert_eq!(boxen("hi", Some(opts)), "┌──────┐\n│    hi│\n└──────┘");
+    }
+
+    #[test]
+    fn test_max_width_caps_wide_content() {
+        let opts = BoxenOptions::new().max_width(10);
+        assert_eq!(boxen("hi", Some(opts.clone())), "┌──┐\n│hi│\n└──┘");
+        assert_eq!(
+            boxen("the quick brown fox", Some(opts.clone())),
+            "┌─────┐\n│the  │\n│quick│\n│brown│\n│fox  │\n└─────┘"
+        );
+
+        // A fixed width beyond the cap is clamped to it
+        let result = boxen("hi", Some(opts.width(40)));
+        assert_eq!(result.lines().map(string_width).max(), Some(10));
+    }
+
+    #[test]
+    fn test_max_width_defaults_to_terminal() {
+        let opts = BoxenOptions::new()
+            .terminal_width(12)
+            .margin(Spacing::horizontal(1));
+        let result = boxen("one two three four", Some(opts));
+        assert_eq!(
+            result,
+            " ┌───────┐ \n │one two│ \n │three  │ \n │four   │ \n └───────┘ "
+        );
+        assert!(result.lines().all(|line| string_width(line) <= 12));
+    }
+
+    #[test]
+    fn test_wrap_moves_straddling_wide_char_down() {
+        // Three interior columns hold one wide char plus a blank column
+        let opts = BoxenOptions::new().width(5);
+        assert_eq!(
+            boxen("古文字", Some(opts)),
+            "┌───┐\n│古 │\n│文 │\n│字 │\n└───┘"
+        );
+
+        let opts = BoxenOptions::new().width(6);
+        assert_eq!(
+            boxen("a古文字", Some(opts)),
+            "┌────┐\n│a古 │\n│文字│\n└────┘"
+        );
+    }
+
+    #[test]
+    fn test_wrap_reopens_color_on_each_row() {
+        let opts = BoxenOptions::new().width(11);
+        let result = boxen("\u{001B}[31mred words that wrap\u{001B}[0m", Some(opts));
+        let rows: Vec<&str> = result.lines().collect();
+
+        let visible: Vec<String> = rows.iter().map(|row| truncate(row, usize::MAX)).collect();
+        assert_eq!(
+            visible,
+            ["┌─────────┐", "│red words│", "│that wrap│", "└─────────┘"]
+        );
+        for row in &rows[1..3] {
+            assert!(row.starts_with("│\u{001B}[31m"), "{:?}", row);
+            assert!(row.ends_with("\u{001B}[0m│"), "{:?}", row);
+        }
+    }
+
+    /// Claims two columns per byte, wider than any Unicode measure
+    struct DoubleBytes;
+
+    impl WidthStrategy for DoubleBytes {
+        fn width(&self, s: &str) -> usize {
+            s.len() * 2
+        }
+    }
+
+    #[test]
+    fn test_boxen_with_mock_strategy() {
+        // "a" claims 2 columns and "abc" 6, so right alignment leads "a"
+        // with 4 spaces; the box is laid out in the strategy's columns
+        let opts = BoxenOptions::new().text_alignment(Alignment::Right);
+        assert_eq!(
+            boxen_with("a\nabc", &opts, &DoubleBytes),
+            "┌──────┐\n│    a│\n│abc│\n└──────┘"
+        );
+        assert_eq!(
+            boxen_with("a\nabc", &opts, &UnicodeWidth),
+            boxen("a\nabc", Some(opts))
+        );
+    }
+
+    #[test]
+    fn test_caption_with_mock_strategy() {
+        // Cut to 6 Unicode columns "abcdef" is still 12 to the strategy, so
+        // the caption keeps only what the strategy fits in the 6-column box
+        let opts = BoxenOptions::new().caption("abcdef");
+        assert_eq!(
+            boxen_with("hi", &opts, &DoubleBytes),
+            "┌────┐\n│hi│\n└────┘\nabc"
+        );
+    }
+
+    #[test]
+    fn test_rtl_mirrors_layout() {
+        let opts = BoxenOptions::new()
+            .title("ab")
+            .rtl(true)
+            .terminal_width(15)
+            .margin(Spacing {
+                left: 2,
+                ..Spacing::default()
+            });
+        let result = boxen("x\nlonger line", Some(opts));
+        assert_eq!(
+            result,
+            "┌─────── ab ┐  \n│          x│  \n│longer line│  \n└───────────┘  "
+        );
+
+        // An explicit alignment is mirrored too
+        let opts = BoxenOptions::new()
+            .rtl(true)
+            .terminal_width(4)
+            .text_alignment(Alignment::Right);
+        assert_eq!(boxen("x\nyy", Some(opts)), "┌──┐\n│x │\n│yy│\n└──┘");
+    }
+
+    #[test]
+    fn test_rtl_places_box_from_right_edge() {
+        let opts = BoxenOptions::new().rtl(true).terminal_width(20);
+        assert_eq!(
+            boxen("hi", Some(opts.clone())),
+            "                ┌──┐\n                │hi│\n                └──┘"
+        );
+
+        // The leading margin is on the right too
+        let margin = Spacing {
+            left: 3,
+            ..Spacing::default()
+        };
+        let result = boxen("hi", Some(opts.clone().margin(margin)));
+        assert_eq!(result.lines().next(), Some("             ┌──┐   "));
+
+        // An explicit float is mirrored like any other alignment
+        let result = boxen("hi", Some(opts.float(Alignment::Right)));
+        assert_eq!(result, "┌──┐\n│hi│\n└──┘");
+    }
+
+    #[test]
+    fn test_even_width_for_cjk() {
+        let padding = Spacing {
+            left: 1,
+            ..Spacing::default()
+        };
+        let opts = BoxenOptions::new()
+            .padding(padding)
+            .text_alignment(Alignment::Center)
+            .even_width(true);
+        let result = boxen("古文字\n中文", Some(opts));
+        let rows: Vec<&str> = result.lines().collect();
+
+        assert_eq!(string_width(rows[0]) - 2, 8);
+        assert_eq!(rows[1], "│ 古文字 │");
+        assert_eq!(rows[2], "│  中文  │");
+
+        // Already-even interiors are left alone
+        let opts = BoxenOptions::new().even_width(true);
+        assert_eq!(boxen("古文", Some(opts)), "┌────┐\n│古文│\n└────┘");
+    }
+
+    #[test]
+    fn test_hide_if_empty() {
+        let opts = BoxenOptions::new().hide_if_empty(true);
+        assert_eq!(boxen(" \n\t\n", Some(opts.clone())), "");
+        assert_eq!(boxen("hi", Some(opts)), "┌──┐\n│hi│\n└──┘");
+
+        let result = boxen(" \n", None);
+        assert_eq!(result, "┌─┐\n│ │\n│ │\n└─┘");
+    }
+
+    #[test]
+    fn test_trim_blank_lines() {
+        let opts = BoxenOptions::new().trim_blank_lines(true);
+        assert_eq!(boxen("\n\nhi\n\n", Some(opts.clone())), "┌──┐\n│hi│\n└──┘");
+        assert_eq!(
+            boxen("\na\n\nb\n ", Some(opts.clone())),
+            "┌─┐\n│a│\n│ │\n│b│\n└─┘"
+        );
+
+        // Trimming happens before padding is applied
+        let opts = opts.padding(Spacing::vertical(1));
+        assert_eq!(
+            boxen("\n\nhi\n\n", Some(opts)),
+            "┌──┐\n│  │\n│hi│\n│  │\n└──┘"
+        );
+    }
+
+    #[test]
+    fn test_zebra_striping() {
+        let primary = Color::Named(NamedColor::BrightBlack);
+        let secondary = Color::Ansi256(236);
+        let opts = BoxenOptions::new()
+            .zebra(primary, secondary)
+            .padding(Spacing::vertical(1));
+        let result = boxen("a\nb\nc", Some(opts));
+        let rows: Vec<&str> = result.lines().collect();
+
+        let plain: Vec<String> = rows.iter().map(|row| truncate(row, usize::MAX)).collect();
+        assert_eq!(plain, ["┌─┐", "│ │", "│a│", "│b│", "│c│", "│ │", "└─┘"]);
+
+        assert_eq!(rows[2], format!("│{}a{}│", primary.bg(), BG_RESET));
+        assert_eq!(rows[3], format!("│{}b{}│", secondary.bg(), BG_RESET));
+        assert_eq!(rows[4], format!("│{}c{}│", primary.bg(), BG_RESET));
+        assert_eq!(rows[1], format!("│{} {}│", primary.bg(), BG_RESET));
+        assert_eq!(rows[5], rows[1]);
+    }
+
+    #[test]
+    fn test_boxen_rendered_content_region() {
+        let opts = BoxenOptions::new()
+            .title("title")
+            .padding(Spacing::sym(2, 1))
+            .margin(Spacing {
+                top: 1,
+                left: 3,
+                ..Spacing::default()
+            });
+        let rendered = boxen_rendered("ab\ncd", &opts);
+        assert_eq!(rendered.lines.join("\n"), boxen("ab\ncd", Some(opts)));
+        assert_eq!(rendered.height, rendered.lines.len());
+        assert!(
+            rendered
+                .lines
+                .iter()
+                .all(|line| string_width(line) == rendered.width)
+        );
+
+        let (row_start, col_start, rows, cols) = rendered.content_region;
+        assert_eq!((row_start, col_start, rows, cols), (3, 6, 2, 3));
+        let content: Vec<String> = rendered.lines[row_start..row_start + rows]
+            .iter()
+            .map(|line| line.chars().skip(col_start).take(cols).collect())
+            .collect();
+        assert_eq!(content, ["ab ", "cd "]);
+
+        let hidden = boxen_rendered(" ", &BoxenOptions::new().hide_if_empty(true));
+        assert_eq!(hidden, RenderedBox::default());
+    }
+
+    #[test]
+    fn test_border_width_two() {
+        let opts = BoxenOptions::new().border_width(2);
+        assert_eq!(
+            boxen("hi", Some(opts.clone())),
+            "┌────┐\n│┌──┐│\n││hi││\n│└──┘│\n└────┘"
+        );
+
+        let rendered = boxen_rendered("hi", &opts.padding(Spacing::horizontal(1)));
+        assert_eq!((rendered.width, rendered.height), (8, 5));
+        assert_eq!(rendered.content_region, (2, 3, 1, 2));
+        assert_eq!(rendered.lines[2], "││ hi ││");
+    }
+
+    #[test]
+    fn test_title_color_separate_from_border() {
+        let border = Color::Named(NamedColor::Blue);
+        let title = Color::Named(NamedColor::Yellow);
+        let opts = BoxenOptions::new()
+            .title("hi")
+            .border_color(border)
+            .title_color(title);
+        let result = boxen("hello", Some(opts));
+        let top = result.lines().next().unwrap();
+
+        assert_eq!(truncate(top, usize::MAX), "┌ hi ─┐");
+        assert_eq!(
+            top,
+            format!(
+                "{}┌{}{} hi {}{}─┐{}",
+                border.fg(),
+                FG_RESET,
+                title.fg(),
+                FG_RESET,
+                border.fg(),
+                FG_RESET
+            )
+        );
+
+        // Without a border color only the title is painted
+        let opts = BoxenOptions::new().title("hi").title_color(title);
+        let result = boxen("hello", Some(opts));
+        assert!(result.starts_with(&format!("┌{} hi {}─┐", title.fg(), FG_RESET)));
+    }
+
+    #[test]
+    fn test_vertical_orientation() {
+        let opts = BoxenOptions::new().orientation(Orientation::VerticalCw);
+        assert_eq!(boxen("AB", Some(opts.clone())), "┌─┐\n│A│\n│B│\n└─┘");
+
+        // The first line ends up on the right, shorter lines leave gaps
+        assert_eq!(
+            boxen("AB\nCDE", Some(opts.clone())),
+            "┌──┐\n│CA│\n│DB│\n│E │\n└──┘"
+        );
+
+        // Wide characters make their column two cells wide
+        assert_eq!(boxen("古a\nxy", Some(opts)), "┌───┐\n│x古│\n│ya │\n└───┘");
+
+        let opts = BoxenOptions::new().orientation(Orientation::VerticalCcw);
+        assert_eq!(boxen("AB\nCDE", Some(opts)), "┌──┐\n│ E│\n│BD│\n│AC│\n└──┘");
+    }
+
+    #[test]
+    fn test_corner_fill() {
+        let opts = BoxenOptions::new().corner_fill(true).title("t");
+        let result = boxen("ab", Some(opts));
+        assert_eq!(result, "▛ t ▜\n│ab │\n▙───▟");
+
+        let rows: Vec<Vec<char>> = result.lines().map(|row| row.chars().collect()).collect();
+        let last = rows.len() - 1;
+        assert_eq!(rows[0][0], '▛');
+        assert_eq!(*rows[0].last().unwrap(), '▜');
+        assert_eq!(rows[last][0], '▙');
+        assert_eq!(*rows[last].last().unwrap(), '▟');
+        assert!(result.lines().all(|row| string_width(row) == 5));
+    }
+
+    #[test]
+    fn test_boxen_into() {
+        let opts = BoxenOptions::new().margin(Spacing::uniform(1));
+
+        let mut buf = String::new();
+        boxen_into(&mut buf, "a\nbc", &opts);
+        assert_eq!(buf, boxen("a\nbc", Some(opts.clone())));
+
+        let mut buf = String::from("first\n");
+        boxen_into(&mut buf, "a\nbc", &opts);
+        assert_eq!(buf, format!("first\n{}", boxen("a\nbc", Some(opts))));
+    }
+
+    #[test]
+    fn test_spacing_scalar_and_uniform() {
+        let scalar = Spacing::scalar(2);
+        assert_eq!(
+            (scalar.top, scalar.right, scalar.bottom, scalar.left),
+            (2, 6, 2, 6)
+        );
+
+        let uniform = Spacing::uniform(2);
+        assert_eq!(
+            (uniform.top, uniform.right, uniform.bottom, uniform.left),
+            (2, 2, 2, 2)
+        );
+        assert_ne!(scalar, uniform);
+
+        assert_eq!(BoxenOptions::new().padding(1).padding, Spacing::scalar(1));
+        let result = boxen("hi", Some(BoxenOptions::new().padding(1)));
+        assert_eq!(
+            result,
+            "┌────────┐\n│        │\n│   hi   │\n│        │\n└────────┘"
+        );
+    }
+
+    #[test]
+    fn test_spacing_horizontal_vertical_sym() {
+        let horizontal = Spacing::horizontal(2);
+        assert_eq!(
+            (
+                horizontal.top,
+                horizontal.right,
+                horizontal.bottom,
+                horizontal.left
+            ),
+            (0, 2, 0, 2)
+        );
+
+        let vertical = Spacing::vertical(2);
+        assert_eq!(
+            (vertical.top, vertical.right, vertical.bottom, vertical.left),
+            (2, 0, 2, 0)
+        );
+
+        let sym = Spacing::sym(3, 1);
+        assert_eq!((sym.top, sym.right, sym.bottom, sym.left), (1, 3, 1, 3));
+    }
+
+    #[test]
+    fn test_spacing_arithmetic() {
+        let padding = Spacing {
+            top: 1,
+            right: 2,
+            bottom: 3,
+            left: 4,
+        };
+        let margin = Spacing {
+            top: 4,
+            right: 0,
+            bottom: 1,
+            left: usize::MAX,
+        };
+        assert_eq!(
+            padding + margin,
+            Spacing {
+                top: 5,
+                right: 2,
+                bottom: 4,
+                left: usize::MAX,
+            }
+        );
+        assert_eq!(
+            padding.max(margin),
+            Spacing {
+                top: 4,
+                right: 2,
+                bottom: 3,
+                left: usize::MAX,
+            }
+        );
+        assert_eq!(
+            padding.saturating_sub(margin),
+            Spacing {
+                top: 0,
+                right: 2,
+                bottom: 2,
+                left: 0,
+            }
+        );
+        assert_eq!(padding + Spacing::default(), padding);
+    }
+
+    #[test]
+    fn test_ascii_style_is_seven_bit() {
+        let chars = BorderStyle::Ascii.chars();
+        assert_eq!(
+            [
+                chars.top_left,
+                chars.top_right,
+                chars.bottom_left,
+                chars.bottom_right
+            ],
+            ["+"; 4]
+        );
+        assert_eq!((chars.horizontal, chars.vertical), ("-", "|"));
+
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Ascii)
+            .title("t")
+            .separators(vec![0])
+            .grid(GridSpec::new(vec![1]));
+        let result = boxen("ab\ncd", Some(opts));
+        assert!(result.bytes().all(|byte| byte < 0x80), "{}", result);
+        assert!(is_boxed(&result));
+    }
+
+    #[test]
+    fn test_border_style_from_str() {
+        assert_eq!("double".parse(), Ok(BorderStyle::Double));
+        assert_eq!("Round".parse(), Ok(BorderStyle::Round));
+        assert_eq!("singleDouble".parse(), Ok(BorderStyle::SingleDouble));
+        assert_eq!("double-single".parse(), Ok(BorderStyle::DoubleSingle));
+        assert!("dotted".parse::<BorderStyle>().is_err());
+    }
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style(Some("double")), BorderStyle::Double);
+        assert_eq!(
+            parse_style(Some(" single-double ")),
+            BorderStyle::SingleDouble
+        );
+        assert_eq!(parse_style(Some("nonsense")), BorderStyle::Single);
+        assert_eq!(parse_style(Some("")), BorderStyle::Single);
+        assert_eq!(parse_style(None), BorderStyle::Single);
+    }
+
+    #[test]
+    fn test_border_style_chars() {
+        assert_eq!(BorderStyle::Round.chars().top_left, "╭");
+        assert_eq!(BorderStyle::Round.chars().bottom_right, "╯");
+        assert_eq!(BorderStyle::Double.chars().horizontal, "═");
+        assert_eq!(BorderStyle::Double.chars().vertical, "║");
+        assert_eq!(BorderStyle::Single.chars().left_tee, "├");
+        assert_eq!(BorderStyle::Classic.chars().top_right, "+");
+
+        let custom = BorderChars {
+            horizontal: "~",
+            ..BorderStyle::Bold.chars()
+        };
+        assert_eq!(BorderStyle::Custom(custom).chars(), custom);
+    }
+
+    #[test]
+    fn test_is_boxed_presets() {
+        for style in [BorderStyle::Single, BorderStyle::Double, BorderStyle::Round] {
+            let opts = BoxenOptions::new().border_style(style);
+            assert!(is_boxed(&boxen("hello\nworld", Some(opts))));
+        }
+
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Classic)
+            .margin(Spacing {
+                top: 1,
+                right: 0,
+                bottom: 1,
+                left: 2,
+            })
+            .separators(vec![0]);
+        assert!(is_boxed(&boxen("a\nb", Some(opts))));
+
+        let opts = BoxenOptions::new()
+            .border_color(Color::Named(NamedColor::Red))
+            .background_color(Color::Named(NamedColor::Blue));
+        assert!(is_boxed(&boxen("hi", Some(opts))));
+    }
+
+    #[test]
+    fn test_is_boxed_plain_text() {
+        assert!(!is_boxed(""));
+        assert!(!is_boxed("hello"));
+        assert!(!is_boxed("hello\nworld"));
+        assert!(!is_boxed("┌─┐"));
+    }
+
+    #[test]
+    fn test_is_boxed_ascii_art_with_plus() {
+        assert!(!is_boxed("1 + 2\n= 3 +"));
+        assert!(!is_boxed("+ one\n+ two\n+ three +"));
+        assert!(!is_boxed("+--+\n  x\n+--+"));
+    }
+
+    #[test]
+    fn test_boxen_lines_matches_boxen() {
+        let opts = BoxenOptions::new();
+        assert_eq!(boxen_lines(&["a", "bb"], &opts), boxen("a\nbb", None));
+
+        let opts = BoxenOptions::new()
+            .text_alignment(Alignment::Center)
+            .separators(vec![0]);
+        assert_eq!(
+            boxen_lines(&["title", "古", "\u{001B}[1mx\u{001B}[0m"], &opts),
+            boxen("title\n古\n\u{001B}[1mx\u{001B}[0m", Some(opts.clone()))
+        );
+    }
+
+    #[test]
+    fn test_boxen_lines_splits_embedded_newlines() {
+        let opts = BoxenOptions::new();
+        assert_eq!(boxen_lines(&["a\nb", "cc"], &opts), boxen("a\nb\ncc", None));
+    }
+
+    #[test]
+    fn test_named_color_codes() {
+        let colors = [
+            (NamedColor::Black, 30, 40),
+            (NamedColor::Red, 31, 41),
+            (NamedColor::Green, 32, 42),
+            (NamedColor::Yellow, 33, 43),
+            (NamedColor::Blue, 34, 44),
+            (NamedColor::Magenta, 35, 45),
+            (NamedColor::Cyan, 36, 46),
+            (NamedColor::White, 37, 47),
+            (NamedColor::BrightBlack, 90, 100),
+            (NamedColor::BrightRed, 91, 101),
+            (NamedColor::BrightGreen, 92, 102),
+            (NamedColor::BrightYellow, 93, 103),
+            (NamedColor::BrightBlue, 94, 104),
+            (NamedColor::BrightMagenta, 95, 105),
+            (NamedColor::BrightCyan, 96, 106),
+            (NamedColor::BrightWhite, 97, 107),
+        ];
+        for (named, fg, bg) in colors {
+            assert_eq!(named.fg_code(), fg);
+            assert_eq!(named.bg_code(), bg);
+            assert_eq!(Color::Named(named).fg(), format!("\u{001B}[{}m", fg));
+            assert_eq!(Color::Named(named).bg(), format!("\u{001B}[{}m", bg));
+        }
+    }
+
+    #[test]
+    fn test_extended_color_codes() {
+        assert_eq!(Color::Ansi256(208).fg(), "\u{001B}[38;5;208m");
+        assert_eq!(Color::Rgb(1, 2, 3).bg(), "\u{001B}[48;2;1;2;3m");
+    }
+
+    #[test]
+    fn test_border_color() {
+        let opts = BoxenOptions::new().border_color(Color::Named(NamedColor::BrightCyan));
+        let result = boxen("hi", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "\u{001B}[96m┌──┐\u{001B}[39m");
+        assert_eq!(
+            lines[1],
+            "\u{001B}[96m│\u{001B}[39mhi\u{001B}[96m│\u{001B}[39m"
+        );
+        for line in lines {
+            assert_eq!(string_width(line), 4);
+        }
+    }
+
+    #[test]
+    fn test_title() {
+        let opts = BoxenOptions::new().title("hi");
+        assert_eq!(boxen("hello", Some(opts)), "┌ hi ─┐\n│hello│\n└─────┘");
+
+        let opts = BoxenOptions::new()
+            .title("hi")
+            .title_alignment(Alignment::Right);
+        assert_eq!(boxen("hello", Some(opts)), "┌─ hi ┐\n│hello│\n└─────┘");
+    }
+
+    #[test]
+    fn test_title_widens_box() {
+        let opts = BoxenOptions::new()
+            .title("long title")
+            .text_alignment(Alignment::Center);
+        let result = boxen("ab", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "┌ long title ┐");
+        // Content stays aligned against its own width
+        assert_eq!(lines[1], "│ab          │");
+    }
+
+    #[test]
+    fn test_content_follows_title_width() {
+        let opts = BoxenOptions::new()
+            .title("long title")
+            .text_alignment(Alignment::Center)
+            .content_follows_title_width(true)
+            .padding(Spacing {
+                top: 0,
+                right: 1,
+                bottom: 0,
+                left: 1,
+            });
+        let result = boxen("ab\nabcd", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "┌ long title ┐");
+        assert_eq!(lines[1], "│     ab     │");
+        assert_eq!(lines[2], "│    abcd    │");
+        assert_eq!(lines[3], "└────────────┘");
+    }
+
+    #[test]
+    fn test_scroll_overflow_window() {
+        let opts = BoxenOptions::new()
+            .overflow(Overflow::Scroll)
+            .terminal_width(20)
+            .scroll_x(5);
+        let result = boxen("0123456789abcdefghijklmnopqrstuvwxyz\nshort", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "┌──────────────────┐");
+        assert_eq!(lines[1], "│◀56789abcdefghijk▶│");
+        assert_eq!(lines[2], "│◀                 │");
+        for line in &lines {
+            assert_eq!(string_width(line), 20);
+        }
+
+        let opts = BoxenOptions::new()
+            .overflow(Overflow::Scroll)
+            .terminal_width(20)
+            .margin(Spacing {
+                top: 0,
+                right: 5,
+                bottom: 0,
+                left: 2,
+            })
+            .scroll_x(5);
+        let result = boxen("0123456789abcdefghijklmnopqrstuvwxyz", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[1], "  │◀56789abcd▶│     ");
+        for line in &lines {
+            assert_eq!(string_width(line), 20);
+        }
+    }
+
+    #[test]
+    fn test_scroll_drops_markers_when_narrow() {
+        let opts = BoxenOptions::new()
+            .overflow(Overflow::Scroll)
+            .terminal_width(3)
+            .scroll_x(2);
+        let result = boxen("abcdef", Some(opts));
+        assert_eq!(result, "┌─┐\n│c│\n└─┘");
+    }
+
+    #[test]
+    fn test_scroll_overflow_wide_chars() {
+        let opts = BoxenOptions::new()
+            .overflow(Overflow::Scroll)
+            .terminal_width(10)
+            .scroll_x(1);
+        let result = boxen("古古古古古古", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        // The first wide char straddles the window's left edge and the last
+        // one its right edge; both become blanks rather than half glyphs
+        assert_eq!(lines[1], "│◀ 古古 ▶│");
+        assert_eq!(string_width(lines[1]), 10);
+    }
+
+    #[test]
+    fn test_scroll_only_when_too_wide() {
+        let opts = BoxenOptions::new()
+            .overflow(Overflow::Scroll)
+            .terminal_width(20)
+            .scroll_x(3);
+        assert_eq!(boxen("hello", Some(opts)), boxen("hello", None));
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        let opts = BoxenOptions::new()
+            .grid(GridSpec::new(vec![4]))
+            .separators(vec![0])
+            .padding(Spacing {
+                top: 1,
+                right: 0,
+                bottom: 0,
+                left: 0,
+            });
+        let result = boxen("key  val\nab   1\nabc  22", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(
+            lines,
+            vec![
+                "┌────┬───┐",
+                "│    │   │",
+                "│key │val│",
+                "├────┼───┤",
+                "│ab  │1  │",
+                "│abc │22 │",
+                "└────┴───┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grid_junctions_follow_style() {
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Double)
+            .grid(GridSpec::new(vec![1, 99]));
+        let result = boxen("a b", Some(opts));
+        assert_eq!(result, "╔═╦═╗\n║a║b║\n╚═╩═╝");
+    }
+
+    #[test]
+    fn test_grid_over_wide_char_and_ansi() {
+        let opts = BoxenOptions::new().grid(GridSpec::new(vec![1]));
+        let result = boxen("古x\n\u{001B}[1mab\u{001B}[0mc", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        // The grid line replaces the right half of the wide char
+        assert_eq!(lines[1], "│ │x│");
+        assert_eq!(lines[2], "│\u{001B}[1ma│\u{001B}[0mc│");
+    }
+
+    #[test]
+    fn test_themes() {
+        let themes = [
+            (Theme::Info, NamedColor::Blue, "ℹ"),
+            (Theme::Warning, NamedColor::Yellow, "⚠"),
+            (Theme::Error, NamedColor::Red, "✖"),
+            (Theme::Success, NamedColor::Green, "✔"),
+        ];
+        for (theme, color, glyph) in themes {
+            let opts = theme.options();
+            assert_eq!(opts.border_color, Some(Color::Named(color)));
+            assert_eq!(opts.border_style, Some(BorderStyle::Round));
+            assert!(opts.title.as_deref().unwrap().starts_with(glyph));
+        }
+    }
+
+    #[test]
+    fn test_boxen_themed() {
+        let result = boxen_themed("done", Theme::Success);
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], "\u{001B}[32m╭ ✔ Success ╮\u{001B}[39m");
+        assert!(lines[1].contains(" done "));
+    }
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(rule(4, '-'), "----");
+        assert_eq!(rule(0, '-'), "");
+        assert_eq!(rule(3, '古'), "古 ");
+        assert_eq!(string_width(&rule(7, '古')), 7);
+    }
+
+    #[test]
+    fn test_separator_after_first_line() {
+        let opts = BoxenOptions::new().separators(vec![0]);
+        let result = boxen("head\na\nb", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(
+            lines,
+            vec!["┌────┐", "│head│", "├────┤", "│a   │", "│b   │", "└────┘"]
+        );
+    }
+
+    #[test]
+    fn test_separator_uses_style_junctions() {
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Double)
+            .separators(vec![0]);
+        let result = boxen("a\nb", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[2], "╠═╣");
+    }
+
+    mod rectangle {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Building blocks for generated content: ASCII, CJK, styled text and
+        /// emoji (including ZWJ and skin-tone sequences)
+        const TOKENS: &[&str] = &[
+            "a",
+            "hello",
+            " ",
+            "  ",
+            "古",
+            "中文",
+            "こんにちは",
+            "\u{001B}[31m",
+            "\u{001B}[1m",
+            "\u{001B}[0m",
+            "\u{001B}[38;5;208m",
+            "😀",
+            "👍\u{1F3FD}",
+            "👨\u{200D}👩\u{200D}👧",
+            "\u{2764}\u{FE0F}",
+            "e\u{0301}",
+        ];
+
+        fn line() -> impl Strategy<Value = String> {
+            prop::collection::vec(prop::sample::select(TOKENS), 0..8)
+                .prop_map(|tokens| tokens.concat())
+        }
+
+        fn spacing() -> impl Strategy<Value = Spacing> {
+            (0..3usize, 0..3usize, 0..3usize, 0..3usize).prop_map(|(top, right, bottom, left)| {
+                Spacing {
+                    top,
+                    right,
+                    bottom,
+                    left,
+                }
+            })
+        }
+
+        fn alignment() -> impl Strategy<Value = Alignment> {
+            prop::sample::select(vec![Alignment::Left, Alignment::Center, Alignment::Right])
+        }
+
+        fn options() -> impl Strategy<Value = BoxenOptions> {
+            (
+                prop::sample::select(BorderStyle::PRESETS.to_vec()),
+                spacing(),
+                spacing(),
+                alignment(),
+                prop::option::of(line()),
+                alignment(),
+                any::<bool>(),
+                prop::collection::vec(0..6usize, 0..3),
+                prop::option::of(prop::collection::vec(0..12usize, 0..3)),
+                prop::option::of(0..24usize),
+                1..3usize,
+            )
+                .prop_map(
+                    |(
+                        border_style,
+                        padding,
+                        margin,
+                        text_alignment,
+                        title,
+                        title_alignment,
+                        content_follows_title_width,
+                        separators,
+                        grid,
+                        width,
+                        border_width,
+                    )| BoxenOptions {
+                        border_style: Some(border_style),
+                        padding,
+                        margin,
+                        text_alignment,
+                        title,
+                        title_alignment,
+                        content_follows_title_width,
+                        separators,
+                        grid: grid.map(GridSpec::new),
+                        width: width.map(Dimension::Cells),
+                        border_width,
+                        ..Default::default()
+                    },
+                )
+        }
+
+        proptest! {
+            #[test]
+            fn test_boxen_output_is_rectangular(
+                lines in prop::collection::vec(line(), 0..6),
+                opts in options(),
+            ) {
+                let result = boxen(lines.join("\n"), Some(opts));
+                let widths: Vec<usize> = result.split('\n').map(string_width).collect();
+                prop_assert!(widths.windows(2).all(|pair| pair[0] == pair[1]), "{:?}", widths);
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_keeps_unset_fields() {
+        let base = BoxenOptions::new()
+            .border_style(BorderStyle::Double)
+            .padding(1)
+            .margin(Spacing::horizontal(2))
+            .text_alignment(Alignment::Center)
+            .title("Base")
+            .border_width(2)
+            .zebra(
+                Color::Named(NamedColor::Blue),
+                Color::Named(NamedColor::Black),
+            );
+        let patch = BoxenOptionsPatch {
+            border_color: Some(Color::Named(NamedColor::Red)),
+            ..Default::default()
+        };
+
+        let merged = base.merge(&patch);
+        assert_eq!(merged.border_color, Some(Color::Named(NamedColor::Red)));
+        assert_eq!(
+            format!("{:?}", merged),
+            format!(
+                "{:?}",
+                base.clone().border_color(Color::Named(NamedColor::Red))
+            )
+        );
+        assert_eq!(
+            boxen("hi", Some(merged)),
+            boxen("hi", Some(base.border_color(Color::Named(NamedColor::Red))))
+        );
+
+        // An empty patch changes nothing
+        let plain = BoxenOptions::new().title("t");
+        let unchanged = plain.merge(&BoxenOptionsPatch::default());
+        assert_eq!(format!("{:?}", unchanged), format!("{:?}", plain));
+    }
+
+    #[test]
+    fn test_background_never_covers_border() {
+        let green = Color::Named(NamedColor::Green);
+        let output = boxen(
+            "hi\nthere",
+            Some(
+                BoxenOptions::new()
+                    .background_color(green)
+                    .border_color(Color::Named(NamedColor::Red))
+                    .padding(1),
+            ),
+        );
+        let bg_on = green.bg();
+        for row in output.lines().skip(1).take(4) {
+            let start = row.find(&bg_on).unwrap();
+            let end = row.find(BG_RESET).unwrap();
+            assert!(!row[start..end].contains('│'), "{:?}", row);
+            assert_eq!(string_width(&row[start..end]), 11);
+            assert!(row[..start].ends_with(&format!("│{}", FG_RESET)));
+            assert!(row[end + BG_RESET.len()..].contains('│'));
+        }
+        // Top and bottom borders carry no background at all
+        assert!(!output.lines().next().unwrap().contains(&bg_on));
+        assert!(!output.lines().last().unwrap().contains(&bg_on));
+    }
+
+    #[test]
+    fn test_overflow_error_with_fixed_width() {
+        let opts = BoxenOptions::new()
+            .width(12)
+            .padding(Spacing::horizontal(1))
+            .overflow(Overflow::Error);
+        let err = try_boxen("fits\nthis line is far too wide", Some(opts.clone())).unwrap_err();
+        assert_eq!(
+            err,
+            OverflowError {
+                line: 1,
+                width: 25,
+                limit: 8
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "line 2 is 25 columns wide, but the box fits at most 8"
+        );
+
+        // Exactly filling the interior is fine
+        assert_eq!(
+            try_boxen("eight ch", Some(opts.clone())).unwrap(),
+            "┌──────────┐\n│ eight ch │\n└──────────┘"
+        );
+
+        // The infallible entry point draws overflowing content unwrapped
+        assert_eq!(
+            boxen("nine chrs", Some(opts)),
+            "┌───────────┐\n│ nine chrs │\n└───────────┘"
+        );
+
+        // Other overflow modes never fail
+        let wrapping = BoxenOptions::new().width(6);
+        assert_eq!(
+            try_boxen("too wide", Some(wrapping.clone())).unwrap(),
+            boxen("too wide", Some(wrapping))
+        );
+    }
+
+    #[test]
+    fn test_overflow_error_counts_expanded_tabs() {
+        let opts = BoxenOptions::new()
+            .width(6)
+            .tab_width(8)
+            .overflow(Overflow::Error);
+        let err = try_boxen("a\tb", Some(opts.clone())).unwrap_err();
+        assert_eq!((err.line, err.width, err.limit), (0, 9, 4));
+
+        // A tab stop that lands inside the interior still fits
+        let opts = opts.tab_width(2);
+        assert_eq!(
+            try_boxen("a\tb", Some(opts)).unwrap(),
+            "┌────┐\n│a b │\n└────┘"
+        );
+    }
+
+    #[test]
+    fn test_tab_width_aligns_columns() {
+        let opts = BoxenOptions::new()
+            .tab_width(4)
+            .padding(Spacing::horizontal(2))
+            .margin(Spacing::horizontal(3));
+        let output = boxen("a\tone\nabc\ttwo\n古\tsix", Some(opts));
+        assert!(!output.contains('\t'));
+
+        let columns: Vec<usize> = output
+            .lines()
+            .filter_map(|row| {
+                let word = ["one", "two", "six"]
+                    .into_iter()
+                    .find(|w| row.contains(w))?;
+                Some(string_width(&row[..row.find(word).unwrap()]))
+            })
+            .collect();
+        // Stops count from the interior, not the margin or border: 3 + 1 + 2
+        // columns lead up to the content, then the first stop at 4
+        assert_eq!(columns, vec![10, 10, 10]);
+    }
+
+    #[test]
+    fn test_boxen_accepts_any_str_like_text() {
+        let expected = "┌──┐\n│hi│\n└──┘";
+        let owned = String::from("hi");
+        assert_eq!(boxen("hi", None), expected);
+        assert_eq!(boxen(&owned, None), expected);
+        assert_eq!(boxen(owned.clone(), None), expected);
+        assert_eq!(boxen(Cow::Borrowed("hi"), None), expected);
+        assert_eq!(try_boxen(owned, None).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unicode_line_breaks() {
+        let text = "one\u{2028}two\u{2029}three";
+        let opts = BoxenOptions::new().unicode_line_breaks(true);
+        assert_eq!(boxen(text, Some(opts)), boxen("one\ntwo\nthree", None));
+
+        // Off by default, the separators are ordinary one-column characters
+        let rendered = boxen_rendered(text, &BoxenOptions::new());
+        assert_eq!((rendered.width, rendered.height), (15, 3));
+    }
+
+    #[test]
+    fn test_boxen_paragraphs() {
+        let text = "first paragraph\nspans two lines\n\n  \n\nsecond";
+        let result = boxen_paragraphs(text, &BoxenOptions::new());
+        assert_eq!(
+            result,
+            [
+                "┌───────────────┐",
+                "│first paragraph│",
+                "│spans two lines│",
+                "└───────────────┘",
+                "┌──────┐",
+                "│second│",
+                "└──────┘",
+            ]
+            .join("\n")
+        );
+
+        // A margin spaces the boxes apart
+        let spaced = boxen_paragraphs("a\n\nb", &BoxenOptions::new().margin(Spacing::vertical(1)));
+        assert_eq!(spaced, "   \n┌─┐\n│a│\n└─┘\n   \n   \n┌─┐\n│b│\n└─┘\n   ");
+
+        assert_eq!(boxen_paragraphs("\n \n", &BoxenOptions::new()), "");
+    }
+
+    #[test]
+    fn test_box_alignment_matches_float() {
+        let base = BoxenOptions::new()
+            .terminal_width(20)
+            .margin(Spacing::horizontal(1));
+        let aligned = boxen("hi", Some(base.clone().box_alignment(Alignment::Right)));
+        let floated = boxen("hi", Some(base.clone().float(Alignment::Right)));
+        assert_eq!(aligned, floated);
+        assert_eq!(aligned.lines().next(), Some("               ┌──┐ "));
+
+        let centered = boxen("hi", Some(base.clone().float(Alignment::Center)));
+        assert_eq!(centered.lines().next(), Some("        ┌──┐ "));
+
+        // When both are set, box_alignment wins
+        let both = base
+            .float(Alignment::Center)
+            .box_alignment(Alignment::Right);
+        assert_eq!(boxen("hi", Some(both)), floated);
+    }
+
+    #[test]
+    fn test_boxen_iter_matches_boxen() {
+        let text = "first\na line long enough to wrap\n\nlast";
+        let stream = || text.split('\n').map(String::from);
+        let opts = BoxenOptions::new()
+            .width(16)
+            .padding(1)
+            .margin(Spacing::sym(2, 1))
+            .title("Log")
+            .text_alignment(Alignment::Right)
+            .zebra(
+                Color::Named(NamedColor::Blue),
+                Color::Named(NamedColor::Black),
+            )
+            .separators(vec![0, 2]);
+        let rows: Vec<String> = boxen_iter(stream(), &opts).collect();
+        assert_eq!(rows.join("\n"), boxen(text, Some(opts)));
+
+        // Without a fixed width the rows still match, after a first pass
+        let opts = BoxenOptions::new().padding(1);
+        let rows: Vec<String> = boxen_iter(stream(), &opts).collect();
+        assert_eq!(rows.join("\n"), boxen(text, Some(opts)));
+
+        let opts = BoxenOptions::new().width(6);
+        let rows: Vec<String> = boxen_iter(std::iter::empty(), &opts).collect();
+        assert_eq!(rows.join("\n"), boxen("", Some(opts.clone())));
+
+        // An endless stream is framed row by row
+        let endless = std::iter::repeat("tick".to_string());
+        let rows: Vec<String> = boxen_iter(endless, &opts).take(3).collect();
+        assert_eq!(rows, ["┌────┐", "│tick│", "│tick│"]);
+    }
+
+    #[test]
+    fn test_smart_alignment_right_aligns_numbers() {
+        let opts = BoxenOptions::new()
+            .text_alignment(Alignment::Smart)
+            .padding(Spacing::horizontal(1));
+        assert_eq!(
+            boxen("total\n42", Some(opts.clone())),
+            "┌───────┐\n│ total │\n│    42 │\n└───────┘"
+        );
+
+        // Right-to-left boxes mirror the choice
+        assert_eq!(
+            boxen("total\n42", Some(opts.rtl(true).terminal_width(9))),
+            "┌───────┐\n│ total │\n│ 42    │\n└───────┘"
+        );
+    }
+
+    #[test]
+    fn test_boxen_kv_lines_up_values() {
+        let pairs = [
+            ("id", "7"),
+            ("\u{001B}[1mstatus\u{001B}[22m", "running"),
+            ("名前", "boxen\nrs"),
+        ];
+        let result = boxen_kv(&pairs, &BoxenOptions::new().padding(Spacing::horizontal(1)));
+        let columns: Vec<usize> = result
+            .lines()
+            .filter_map(|row| {
+                let value = ["7", "running", "boxen", "rs"]
+                    .into_iter()
+                    .find(|value| row.contains(&format!(" {} ", value)))?;
+                Some(string_width(&row[..row.find(value).unwrap()]))
+            })
+            .collect();
+        assert_eq!(columns, vec![10, 10, 10, 10]);
+        assert!(result.contains("│ id:     7       │"));
+    }
+
+    #[test]
+    fn test_sgr_spanning_lines_stays_off_borders() {
+        let text = "\u{001B}[31mone\ntwo\nthree\u{001B}[39m\nplain";
+        let result = boxen(text, None);
+        let rows: Vec<&str> = result.split('\n').collect();
+        assert_eq!(rows[1], "│\u{001B}[31mone\u{001B}[0m  │");
+        assert_eq!(rows[2], "│\u{001B}[31mtwo\u{001B}[0m  │");
+        assert_eq!(rows[3], "│\u{001B}[31mthree\u{001B}[39m│");
+        assert_eq!(rows[4], "│plain│");
+
+        // Streaming carries the span the same way
+        let opts = BoxenOptions::new().width(7);
+        let rows: Vec<String> = boxen_iter(text.split('\n').map(String::from), &opts).collect();
+        assert_eq!(rows.join("\n"), boxen(text, Some(opts)));
+    }
+
+    #[test]
+    fn test_track_sgr_groups_parameters() {
+        let mut active = Vec::new();
+        track_sgr(&mut active, "\u{001B}[1;31mbold red\u{001B}[32m");
+        assert_eq!(reopen_sgr(&active), "\u{001B}[1;32m");
+        track_sgr(&mut active, "\u{001B}[48;2;1;2;3m\u{001B}[22m");
+        assert_eq!(reopen_sgr(&active), "\u{001B}[32;48;2;1;2;3m");
+        track_sgr(&mut active, "\u{001B}[39;49m");
+        assert_eq!(reopen_sgr(&active), "");
+        track_sgr(&mut active, "\u{001B}[4m\u{001B}[2K\u{001B}[m");
+        assert!(active.is_empty());
+    }
+
+    #[test]
+    fn test_ascii_when_piped() {
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Double)
+            .corner_fill(true)
+            .ascii_when_piped(true);
+        assert_eq!(
+            boxen("hi", Some(opts.clone().is_tty(false))),
+            "+--+\n|hi|\n+--+"
+        );
+        assert_eq!(
+            boxen("hi", Some(opts.clone().is_tty(true))),
+            "▛══▜\n║hi║\n▙══▟"
+        );
+
+        // Without the option, piped output keeps its style
+        let keep = opts
+            .ascii_when_piped(false)
+            .corner_fill(false)
+            .is_tty(false);
+        assert_eq!(boxen("hi", Some(keep)), "╔══╗\n║hi║\n╚══╝");
+    }
+
+    #[test]
+    fn test_box_diff() {
+        let opts = BoxenOptions::new().border_color(Color::Named(NamedColor::Red));
+        let colored = boxen("same\ntext", Some(opts));
+        assert_eq!(box_diff(&colored, &boxen("same\ntext", None)), None);
+
+        assert_eq!(
+            box_diff(&boxen("古ab\nx", None), &boxen("古ac\nx", None)).unwrap(),
+            "row 2, column 5 differs\n  left:  │古ab│\n  right: │古ac│"
+        );
+        assert_eq!(
+            box_diff(&boxen("a\nb", None), &boxen("a", None)).unwrap(),
+            "row 3, column 1 differs\n  left:  │b│\n  right: └─┘"
+        );
+        assert_eq!(
+            box_diff(&boxen("a", None), &boxen("a\nb", None)).unwrap(),
+            "row 3, column 1 differs\n  left:  └─┘\n  right: │b│"
+        );
+        assert_eq!(
+            box_diff("x", "x\ny").unwrap(),
+            "row 2 is only on the right: y"
+        );
+    }
+
+    #[test]
+    fn test_percentage_width_and_margin() {
+        let opts = BoxenOptions::new()
+            .terminal_width(80)
+            .width(Dimension::Percent(50));
+        let rendered = boxen_rendered("hi", &opts);
+        assert_eq!(rendered.width, 40);
+
+        // Shares are rounded down: 33% of 80 is 26.4
+        let opts = opts
+            .width(Dimension::Percent(33))
+            .margin_x(Dimension::Percent(5));
+        let result = boxen("hi", Some(opts.clone()));
+        let top = result.lines().next().unwrap();
+        assert_eq!(string_width(top), 4 + 26 + 4);
+        assert!(top.starts_with("    ┌"));
+
+        // Percentages follow the terminal they are drawn on
+        let narrow = boxen_rendered("hi", &opts.terminal_width(40));
+        assert_eq!(narrow.width, 2 + 13 + 2);
+
+        // A width wider than the terminal is still capped by it
+        let wide = BoxenOptions::new()
+            .terminal_width(20)
+            .width(Dimension::Percent(150));
+        assert_eq!(boxen_rendered("hi", &wide).width, 20);
+    }
+
+    #[test]
+    fn test_margin_x_overrides_margin_sides() {
+        let opts = BoxenOptions::new()
+            .terminal_width(40)
+            .margin(Spacing {
+                top: 1,
+                right: 7,
+                bottom: 0,
+                left: 9,
+            })
+            .margin_x(Dimension::Percent(10));
+        let result = boxen("hi", Some(opts));
+        let rows: Vec<&str> = result.split('\n').collect();
+
+        // Top and bottom still come from `margin`; the sides are 10% of 40
+        assert_eq!(rows.len(), 1 + 3);
+        assert_eq!(rows[0], " ".repeat(4 + 4 + 4));
+        assert_eq!(rows[1], "    ┌──┐    ");
+    }
+
+    #[test]
+    fn test_markup() {
+        let opts = BoxenOptions::new().markup(true);
+        assert_eq!(
+            boxen("[red]hi[/] there", Some(opts.clone())),
+            "┌────────┐\n│\u{001B}[31mhi\u{001B}[39m there│\n└────────┘"
+        );
+
+        // Nested tags restore the outer one; unknown tags stay literal
+        assert_eq!(
+            boxen("[bold][dim]a[/]b[/] [x] [", Some(opts.clone())),
+            "┌────────┐\n│\u{001B}[1m\u{001B}[2ma\u{001B}[22m\u{001B}[1mb\u{001B}[22m [x] [│\n└────────┘"
+        );
+
+        // A tag left open continues onto the next line
+        let rows: Vec<String> = boxen("[green]a\nb[/]", Some(opts))
+            .split('\n')
+            .map(String::from)
+            .collect();
+        assert_eq!(rows[1], "│\u{001B}[32ma\u{001B}[0m│");
+        assert_eq!(rows[2], "│\u{001B}[32mb\u{001B}[39m│");
+
+        // Off by default
+        assert_eq!(
+            boxen("[red]hi[/]", None),
+            "┌──────────┐\n│[red]hi[/]│\n└──────────┘"
+        );
+
+        // Tags take no room when checking for overflow
+        let strict = BoxenOptions::new()
+            .markup(true)
+            .width(4)
+            .overflow(Overflow::Error);
+        assert!(try_boxen("[bold]hi[/]", Some(strict)).is_ok());
+    }
+
+    #[test]
+    fn test_border_colors() {
+        let [top, right, bottom, left] = [
+            NamedColor::Red,
+            NamedColor::Green,
+            NamedColor::Blue,
+            NamedColor::Yellow,
+        ]
+        .map(Color::Named);
+        let opts = BoxenOptions::new()
+            .separators(vec![0])
+            .border_colors(EdgeColors {
+                top,
+                right,
+                bottom,
+                left,
+            });
+        let result = boxen("a\nb", Some(opts.clone()));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[0], format!("{}┌─┐{}", top.fg(), FG_RESET));
+        assert_eq!(
+            lines[1],
+            format!("{}│{}a{}│{}", left.fg(), FG_RESET, right.fg(), FG_RESET)
+        );
+        assert_eq!(
+            lines[2],
+            format!("{}├{}─{}┤{}", left.fg(), FG_RESET, right.fg(), FG_RESET)
+        );
+        assert_eq!(lines[4], format!("{}└─┘{}", bottom.fg(), FG_RESET));
+
+        // The layout is the same as an uncolored box
+        assert_eq!(
+            string_width::strip_ansi(&result),
+            boxen("a\nb", Some(BoxenOptions::new().separators(vec![0])))
+        );
+
+        // `border_color` still colors the interior separator run
+        let result = boxen(
+            "a\nb",
+            Some(opts.border_color(Color::Named(NamedColor::Cyan))),
+        );
+        let separator = result.split('\n').nth(2).unwrap().to_string();
+        assert!(separator.contains(&format!("\u{001B}[36m─{}", FG_RESET)));
+    }
+
+    #[test]
+    fn test_fill_ratio() {
+        // Ten columns of interior, half of them filled
+        let opts = BoxenOptions::new().width(12).fill_ratio(0.5);
+        let result = boxen("progress", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[1], format!("│{}progr{}ess  │", REVERSE, REVERSE_OFF));
+        assert_eq!(string_width(lines[1]), 12);
+
+        // A wide character straddling the boundary is filled whole
+        let opts = BoxenOptions::new().width(6).fill_ratio(0.5);
+        let result = boxen("a古b", Some(opts));
+        assert_eq!(
+            result.split('\n').nth(1).unwrap(),
+            format!("│{}a古{}b│", REVERSE, REVERSE_OFF)
+        );
+
+        // Padding rows are part of the interior too; out of range ratios clamp
+        let opts = BoxenOptions::new()
+            .padding(Spacing::from(1))
+            .fill_ratio(2.0);
+        let result = boxen("hi", Some(opts));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[1], format!("│{}        {}│", REVERSE, REVERSE_OFF));
+        assert_eq!(lines[2], format!("│{}   hi   {}│", REVERSE, REVERSE_OFF));
+        assert_eq!(
+            boxen("hi", Some(BoxenOptions::new().fill_ratio(0.0))),
+            boxen("hi", None)
+        );
+
+        // A fill color paints the head's background instead
+        let green = Color::Named(NamedColor::Green);
+        let opts = BoxenOptions::new()
+            .width(6)
+            .fill_ratio(0.5)
+            .fill_color(green);
+        let result = boxen("abcd", Some(opts.clone()));
+        assert_eq!(
+            result.split('\n').nth(1).unwrap(),
+            format!("│\u{001B}[42mab{}cd│", BG_RESET)
+        );
+
+        // Styling that resets inside the filled part doesn't end the fill
+        let result = boxen("\u{001B}[1ma\u{001B}[0mbcd", Some(opts));
+        assert_eq!(
+            result.split('\n').nth(1).unwrap(),
+            format!(
+                "│\u{001B}[42m\u{001B}[1ma\u{001B}[0m\u{001B}[42mb{}cd│",
+                BG_RESET
+            )
+        );
+        let result = boxen(
+            "\u{001B}[7ma\u{001B}[27mbcd",
+            Some(BoxenOptions::new().width(6).fill_ratio(0.5)),
+        );
+        assert_eq!(
+            result.split('\n').nth(1).unwrap(),
+            format!("│{0}{0}a{1}{0}b{1}cd│", REVERSE, REVERSE_OFF)
+        );
+    }
+
+    #[test]
+    fn test_caption() {
+        let opts = BoxenOptions::new().caption("Fig. 1");
+        assert_eq!(
+            boxen("some content", Some(opts.clone())),
+            "┌────────────┐\n│some content│\n└────────────┘\nFig. 1        "
+        );
+
+        // Aligned within the box, which margins shift but don't widen
+        let opts = opts.caption_alignment(Alignment::Center).margin(Spacing {
+            top: 0,
+            right: 1,
+            bottom: 1,
+            left: 2,
+        });
+        let result = boxen("some content", Some(opts.clone()));
+        let lines: Vec<&str> = result.split('\n').collect();
+        assert_eq!(lines[2], "  └────────────┘ ");
+        assert_eq!(lines[3], "      Fig. 1     ");
+        assert_eq!(lines[4], " ".repeat(17));
+
+        // A long caption is cut to the box width rather than widening it
+        let opts = BoxenOptions::new().caption("a very long caption");
+        let result = boxen("hi", Some(opts));
+        assert_eq!(result.split('\n').next_back(), Some("a ve"));
+        assert_eq!(
+            boxen_rendered("hi", &BoxenOptions::new().caption("x")).content_region,
+            boxen_rendered("hi", &BoxenOptions::new()).content_region
+        );
+    }
+
+    #[test]
+    fn test_boxen_grid() {
+        let cyan = Color::Named(NamedColor::Cyan);
+        let blue = Color::Ansi256(19);
+        let opts = BoxenOptions::new()
+            .border_color(cyan)
+            .background_color(blue);
+        let grid = boxen_grid("\u{001B}[1;38;2;1;2;3m古\u{001B}[0mx", &opts);
+        assert_eq!(grid.len(), 3);
+        assert!(grid.iter().all(|row| row.len() == 5));
+
+        // Border glyphs carry the border color and no background
+        assert_eq!(
+            grid[0][0],
+            Cell {
+                ch: '┌',
+                fg: Some(cyan),
+                ..Cell::default()
+            }
+        );
+        assert_eq!(
+            (grid[1][4].ch, grid[1][4].fg, grid[1][4].bg),
+            ('│', Some(cyan), None)
+        );
+
+        // The wide character takes a cell and a continuation cell
+        let bold = Attrs {
+            bold: true,
+            ..Attrs::default()
+        };
+        let glyph = Cell {
+            ch: '古',
+            fg: Some(Color::Rgb(1, 2, 3)),
+            bg: Some(blue),
+            attrs: bold,
+        };
+        assert_eq!(grid[1][1], glyph);
+        assert!(grid[1][2].is_continuation());
+        assert_eq!(grid[1][2].fg, glyph.fg);
+
+        // The reset inside the content clears the text style but keeps the
+        // interior background, padding included
+        assert_eq!(
+            grid[1][3],
+            Cell {
+                ch: 'x',
+                bg: Some(blue),
+                ..Cell::default()
+            }
+        );
+        let grid = boxen_grid("\u{001B}[31ma\u{001B}[0mb", &opts.width(6));
+        assert!(grid[1][1..5].iter().all(|cell| cell.bg == Some(blue)));
+        assert_eq!(grid[1][2].fg, None);
+        assert_eq!(grid[1][5].bg, None);
+    }
+
+    #[test]
+    fn test_boxen_grid_skips_unknown_sgr_params() {
+        let red = Color::Named(NamedColor::Red);
+        let styled = Cell {
+            ch: 'b',
+            fg: Some(red),
+            bg: None,
+            attrs: Attrs {
+                bold: true,
+                ..Attrs::default()
+            },
+        };
+        let text = "\u{001B}[1;31ma\u{001B}[300mb\u{001B}[38;5;300mc\u{001B}[;4md";
+        let grid = boxen_grid(text, &BoxenOptions::new());
+        assert_eq!(grid[1][2], styled);
+        assert_eq!(grid[1][3], Cell { ch: 'c', ..styled });
+
+        // An empty parameter is still a reset
+        assert_eq!(
+            grid[1][4],
+            Cell {
+                ch: 'd',
+                attrs: Attrs {
+                    underline: true,
+                    ..Attrs::default()
+                },
+                ..Cell::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_enclosing_mark_stays_rectangular() {
+        let result = boxen("A\u{20DD}\nBC", None);
+        assert_eq!(result, "┌──┐\n│A\u{20DD} │\n│BC│\n└──┘");
+        for line in result.split('\n') {
+            assert_eq!(string_width(line), 4);
+        }
+    }
+
+    #[test]
+    fn test_code_block() {
+        let code = "fn main() {\n    if ready {\n\tgo();\n    }\n}\n";
+        let opts = BoxenOptions::new()
+            .code_block(true)
+            .tab_width(8)
+            .text_alignment(Alignment::Center)
+            .trim_blank_lines(true)
+            .padding(Spacing {
+                top: 0,
+                right: 1,
+                bottom: 0,
+                left: 1,
+            });
+        let result = boxen(code, Some(opts.clone()));
+        assert_eq!(
+            result,
+            [
+                "┌────────────────┐",
+                "│ fn main() {    │",
+                "│     if ready { │",
+                "│         go();  │",
+                "│     }          │",
+                "│ }              │",
+                "│                │",
+                "└────────────────┘",
+            ]
+            .join("\n")
+        );
+        assert!(result.split('\n').all(|line| string_width(line) == 18));
+
+        // Long lines break at the edge, keeping their runs of spaces
+        let result = boxen(
+            "a  b  c  d",
+            Some(BoxenOptions::new().code_block(true).width(6)),
+        );
+        assert_eq!(result, "┌────┐\n│a  b│\n│  c │\n│ d  │\n└────┘");
+
+        // Brackets are code, not markup
+        let result = boxen("v[red]", Some(opts.markup(true)));
+        assert_eq!(result.split('\n').nth(1), Some("│ v[red] │"));
+    }
+
+    #[test]
+    fn test_bleed() {
+        let white = Color::Named(NamedColor::White);
+        let opts = BoxenOptions::new().bleed(1, white).margin(Spacing {
+            top: 0,
+            right: 0,
+            bottom: 0,
+            left: 2,
+        });
+        let result = boxen("hi", Some(opts.clone()));
+        let mat = |width: usize| format!("\u{001B}[47m{}\u{001B}[49m", " ".repeat(width));
+        assert_eq!(
+            result,
+            [
+                format!("  {}", mat(6)),
+                format!("  {}┌──┐{}", mat(1), mat(1)),
+                format!("  {}│hi│{}", mat(1), mat(1)),
+                format!("  {}└──┘{}", mat(1), mat(1)),
+                format!("  {}", mat(6)),
+            ]
+            .join("\n")
+        );
+
+        // The box grows by the bleed on every side and content moves in
+        let rendered = boxen_rendered("hi", &opts.bleed(2, white));
+        assert_eq!((rendered.width, rendered.height), (10, 7));
+        assert_eq!(rendered.content_region, (3, 5, 1, 2));
+        assert!(rendered.lines.iter().all(|line| string_width(line) == 10));
+    }
+
+    #[test]
+    fn test_corner_style() {
+        let opts = BoxenOptions::new().corner_style(CornerStyle::Cut);
+        let result = boxen("ab", Some(opts.clone()));
+        assert_eq!(result, "╱──╲\n│ab│\n╲──╱");
+
+        // The edges run unbroken between the chamfers, through every ring
+        let result = boxen("ab", Some(opts.border_width(2)));
+        let rows: Vec<&str> = result.split('\n').collect();
+        assert_eq!(rows[0], "╱────╲");
+        assert_eq!(rows[1], "│╱──╲│");
+        assert_eq!(rows[rows.len() - 2], "│╲──╱│");
+        assert!(result.split('\n').all(|row| string_width(row) == 6));
+
+        // Round and sharp corners apply whatever the edge style
+        let double = BoxenOptions::new().border_style(BorderStyle::Double);
+        assert_eq!(
+            boxen("ab", Some(double.corner_style(CornerStyle::Round))),
+            "╭══╮\n║ab║\n╰══╯"
+        );
+        let round = BoxenOptions::new().border_style(BorderStyle::Round);
+        assert_eq!(
+            boxen("ab", Some(round.corner_style(CornerStyle::Sharp))),
+            "┌──┐\n│ab│\n└──┘"
+        );
+    }
+
+    #[test]
+    fn test_corner_styles() {
+        let tab = CornerQuad {
+            top_left: CornerStyle::Round,
+            top_right: CornerStyle::Round,
+            bottom_left: CornerStyle::Sharp,
+            bottom_right: CornerStyle::Sharp,
+        };
+        let result = boxen("ab", Some(BoxenOptions::new().corner_styles(tab)));
+        assert_eq!(result, "╭──╮\n│ab│\n└──┘");
+
+        let rows: Vec<Vec<char>> = result.lines().map(|row| row.chars().collect()).collect();
+        let corners = [rows[0][0], rows[0][3], rows[2][0], rows[2][3]];
+        assert_eq!(corners, ['╭', '╮', '└', '┘']);
+
+        // Per-corner shapes override the box-wide one, even on a round style
+        let opts = BoxenOptions::new()
+            .border_style(BorderStyle::Round)
+            .corner_style(CornerStyle::Cut)
+            .corner_styles(CornerQuad {
+                bottom_right: CornerStyle::Cut,
+                ..tab
+            });
+        assert_eq!(boxen("ab", Some(opts)), "╭──╮\n│ab│\n└──╱");
+    }
+}
@@ -0,0 +1,47 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use string_width::{string_width, string_width_batch, widest_line};
+
+fn sample_lines() -> Vec<String> {
+    (0..64)
+        .map(|i| match i % 4 {
+            0 => format!("plain ascii line number {}", i),
+            1 => format!("古文字 CJK line {}", i),
+            2 => format!("\u{001B}[1m\u{001B}[32mstyled line {}\u{001B}[0m", i),
+            _ => format!("emoji 👨\u{200D}👩\u{200D}👧 line {}", i),
+        })
+        .collect()
+}
+
+fn bench_widths(c: &mut Criterion) {
+    let owned = sample_lines();
+    let lines: Vec<&str> = owned.iter().map(String::as_str).collect();
+
+    c.bench_function("string_width per line", |b| {
+        b.iter(|| {
+            black_box(&lines)
+                .iter()
+                .map(|line| string_width(line))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    c.bench_function("string_width_batch", |b| {
+        b.iter(|| string_width_batch(black_box(&lines)))
+    });
+}
+
+fn bench_widest_line(c: &mut Criterion) {
+    let single = "a single line of ascii text, the common case in a box";
+
+    c.bench_function("widest_line single line", |b| {
+        b.iter(|| widest_line(black_box(single)))
+    });
+
+    c.bench_function("string_width single line", |b| {
+        b.iter(|| string_width(black_box(single)))
+    });
+}
+
+criterion_group!(benches, bench_widths, bench_widest_line);
+criterion_main!(benches);
@@ -1,9 +1,21 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+use unicode_segmentation::UnicodeSegmentation;
+
 /// Calculate the display width of a string, handling Unicode characters and ANSI escape sequences.
 ///
 /// This function strips ANSI escape sequences and then calculates the visual width
 /// of the remaining text, taking into account that some Unicode characters (like CJK)
 /// take up more than one column in a terminal.
 ///
+/// Malformed escapes degrade gracefully rather than swallowing the rest of
+/// the line; see [`ansi_sequence_len`] for exactly where each one ends.
+///
+/// The width never exceeds two columns per `char`: tabs are measured as
+/// control characters (0) rather than expanded, and backspaces only move
+/// back. Use [`expand_tabs`] first when tab stops matter.
+///
 /// # Examples
 ///
 /// ```
@@ -14,16 +26,574 @@
 /// assert_eq!(string_width("\u{001B}[1m古\u{001B}[22m"), 2);
 /// ```
 pub fn string_width(s: &str) -> usize {
-    // Strip ANSI escape sequences first
-    let stripped = strip_ansi_escapes::strip(s);
-    let clean_str = std::str::from_utf8(&stripped).unwrap_or("");
+    measure(s, &WidthOptions::default())
+}
+
+/// Options that adjust how display width is measured
+#[derive(Debug, Clone, Default)]
+pub struct WidthOptions {
+    /// Measure every emoji cluster at this width instead of the width
+    /// `unicode-width` reports, since terminals disagree (default: None)
+    pub emoji_width: Option<usize>,
+    /// Per-character widths that replace what `unicode-width` reports, for
+    /// scripts a given terminal renders differently, e.g. enclosing marks
+    /// like `\u{20DD}` that some terminals draw a column wider, or the
+    /// half-width sound marks `ﾞ` and `ﾟ` that `unicode-width` measures as 0
+    /// but terminals give a column each (default: empty)
+    pub overrides: HashMap<char, usize>,
+    /// Treat LINE SEPARATOR (`\u{2028}`) and PARAGRAPH SEPARATOR
+    /// (`\u{2029}`) as line breaks in [`widest_line_with`], alongside `\n`
+    /// (default: false)
+    pub unicode_line_breaks: bool,
+    /// The width of each control character other than `\n`, for viewers
+    /// that draw them visibly (e.g. `^A` is 2). When nonzero, backspaces and
+    /// tabs are counted at this width too instead of moving the column
+    /// (default: 0)
+    pub control_width: usize,
+}
+
+impl WidthOptions {
+    /// Create new options with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the width every emoji is measured at
+    pub fn emoji_width(mut self, emoji_width: usize) -> Self {
+        self.emoji_width = Some(emoji_width);
+        self
+    }
+
+    /// Measure `c` at `width` wherever it appears
+    pub fn override_width(mut self, c: char, width: usize) -> Self {
+        self.overrides.insert(c, width);
+        self
+    }
+
+    /// Set whether the Unicode line and paragraph separators break lines
+    pub fn unicode_line_breaks(mut self, unicode_line_breaks: bool) -> Self {
+        self.unicode_line_breaks = unicode_line_breaks;
+        self
+    }
+
+    /// Set the width every visible control character is measured at
+    pub fn control_width(mut self, control_width: usize) -> Self {
+        self.control_width = control_width;
+        self
+    }
+}
+
+/// A pluggable way of measuring display width, for targets whose idea of a
+/// column differs from the terminal default (custom fonts, proportional
+/// renderers).
+///
+/// Alignment and box drawing accept any strategy; [`UnicodeWidth`] is the
+/// behavior of [`string_width`].
+///
+/// # Examples
+///
+/// ```
+/// use string_width::{UnicodeWidth, WidthStrategy};
+///
+/// struct Bytes;
+///
+/// impl WidthStrategy for Bytes {
+///     fn width(&self, s: &str) -> usize {
+///         s.len()
+///     }
+/// }
+///
+/// assert_eq!(Bytes.width("古"), 3);
+/// assert_eq!(UnicodeWidth.width("古"), 2);
+/// ```
+pub trait WidthStrategy {
+    /// The display width of `s`, in columns
+    fn width(&self, s: &str) -> usize;
+}
+
+/// The default strategy: ANSI-stripped Unicode width, as [`string_width`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeWidth;
+
+impl WidthStrategy for UnicodeWidth {
+    fn width(&self, s: &str) -> usize {
+        string_width(s)
+    }
+}
+
+/// A strategy for non-monospace targets: each `char` advances by the width
+/// given in the table, or 1 if it isn't listed. ANSI escapes are stripped
+/// first and measure 0.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use string_width::{FixedTable, WidthStrategy};
+///
+/// let table = FixedTable(HashMap::from([('i', 0), ('W', 2)]));
+/// assert_eq!(table.width("Wiki"), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FixedTable(pub HashMap<char, usize>);
+
+impl WidthStrategy for FixedTable {
+    fn width(&self, s: &str) -> usize {
+        strip_ansi(s)
+            .chars()
+            .map(|c| self.0.get(&c).copied().unwrap_or(1))
+            .sum()
+    }
+}
+
+/// Measure with these options, as [`string_width_with`]
+impl WidthStrategy for WidthOptions {
+    fn width(&self, s: &str) -> usize {
+        measure(s, self)
+    }
+}
+
+/// Calculate the display width of a string using custom measuring options.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::{string_width_with, WidthOptions};
+///
+/// let opts = WidthOptions::new().emoji_width(1);
+/// assert_eq!(string_width_with("hi😀", &opts), 3);
+/// assert_eq!(string_width_with("hi😀", &WidthOptions::default()), 4);
+/// ```
+pub fn string_width_with(s: &str, opts: &WidthOptions) -> usize {
+    measure(s, opts)
+}
 
-    // Calculate Unicode width, treating control characters as width 0
+/// Return both the byte length and the display width of a string.
+///
+/// Useful when sizing fixed terminal buffers, which need the raw length
+/// including escape sequences as well as the visible column count. ANSI
+/// codes are stripped only once.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::raw_and_visible_width;
+///
+/// assert_eq!(raw_and_visible_width("\u{001B}[31mhi\u{001B}[0m"), (11, 2));
+/// ```
+pub fn raw_and_visible_width(s: &str) -> (usize, usize) {
+    (s.len(), measure(s, &WidthOptions::default()))
+}
+
+/// Strip ANSI escape sequences from `s` and measure what remains.
+///
+/// Stripping discards control characters, so backspaces are handled
+/// here first: each one moves the running column back by one (never below
+/// 0), letting later characters overwrite earlier ones. The result is the
+/// furthest column reached, since overwritten text doesn't shrink what's
+/// visible.
+fn measure(s: &str, opts: &WidthOptions) -> usize {
+    // Controls that are drawn keep their place; only escapes are dropped
+    if opts.control_width > 0 {
+        return visible_width(&strip_escapes(s), opts);
+    }
+
+    let mut column: usize = 0;
+    let mut furthest = 0;
+    for (index, segment) in s.split('\u{0008}').enumerate() {
+        if index > 0 {
+            column = column.saturating_sub(1);
+        }
+
+        column += visible_width(&strip_ansi(segment), opts);
+        furthest = furthest.max(column);
+    }
+    furthest
+}
+
+/// Calculate the display width of a string without allocating.
+///
+/// Gives the same result as [`string_width`], but escape sequences are
+/// skipped and characters measured in a single pass over `s`, with no
+/// stripped copy. Suited to hot loops and allocation-sensitive code.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::{string_width, string_width_no_alloc};
+///
+/// let s = "\u{001B}[1m古\u{001B}[22m e\u{0301}";
+/// assert_eq!(string_width_no_alloc(s), 4);
+/// assert_eq!(string_width_no_alloc(s), string_width(s));
+/// ```
+pub fn string_width_no_alloc(s: &str) -> usize {
+    let mut rest = s;
+    let visible = std::iter::from_fn(move || {
+        loop {
+            let c = rest.chars().next()?;
+            if c == '\u{001B}' {
+                rest = &rest[ansi_sequence_len(rest)..];
+                continue;
+            }
+            rest = &rest[c.len_utf8()..];
+            return Some(c);
+        }
+    });
+    width_of(visible)
+}
+
+/// Calculate the display width of a stream of characters.
+///
+/// Measures exactly as [`string_width`] does, a character at a time, for
+/// callers composing text from a `char` iterator who would otherwise
+/// collect it into a `String` first. The input is assumed to be free of ANSI
+/// escape sequences already: an `ESC` is ignored like any other control
+/// character, but the parameters after it are counted as text.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::width_of;
+///
+/// assert_eq!(width_of("古 e\u{0301}".chars()), 4);
+/// assert_eq!(width_of(['a', '\u{0008}', 'b']), 1);
+/// ```
+pub fn width_of<I: IntoIterator<Item = char>>(chars: I) -> usize {
+    let mut column: usize = 0;
+    let mut furthest = 0;
+    // The last character kept, which decides whether an Extend codepoint
+    // attaches to a cluster or starts its own
+    let mut previous: Option<char> = None;
+    for c in chars {
+        if c == '\u{0008}' {
+            column = column.saturating_sub(1);
+            previous = None;
+            continue;
+        }
+        if c.is_control() && c != '\n' {
+            continue;
+        }
+        if in_table(c, EXTEND_NONZERO) {
+            if !previous.is_some_and(|p| joins(p, c)) {
+                column += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+            }
+        } else if is_combining_mark(c) {
+            if !previous.is_some_and(|p| joins(p, c)) {
+                column += 1;
+            }
+        } else {
+            column += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        }
+        furthest = furthest.max(column);
+        previous = Some(c);
+    }
+    furthest
+}
+
+/// Calculate the display width of many strings at once.
+///
+/// Equivalent to calling `string_width` on each string, but the buffer that
+/// holds the ANSI-stripped text is allocated once and reused for every string.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::string_width_batch;
+///
+/// assert_eq!(string_width_batch(&["a", "古", "\u{001B}[1mhi\u{001B}[22m"]), vec![1, 2, 2]);
+/// ```
+pub fn string_width_batch(lines: &[&str]) -> Vec<usize> {
+    let mut buf = String::new();
+    lines
+        .iter()
+        .map(|line| {
+            if line.contains('\u{0008}') {
+                return string_width(line);
+            }
+
+            buf.clear();
+            strip_ansi_into(line, &mut buf);
+            visible_width(&buf, &WidthOptions::default())
+        })
+        .collect()
+}
+
+/// Remove ANSI escape sequences and control characters (other than `\n`)
+/// from `s`, borrowing it when there is nothing to remove.
+///
+/// This is the text [`string_width`] measures.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::strip_ansi;
+///
+/// assert_eq!(strip_ansi("\u{001B}[31mred\u{001B}[0m\u{0007}"), "red");
+/// assert_eq!(strip_ansi("a\nb"), "a\nb");
+/// ```
+pub fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(is_strippable) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    strip_ansi_into(s, &mut out);
+    Cow::Owned(out)
+}
+
+/// Append `s` to `out` with escape sequences and controls removed, as
+/// [`strip_ansi`].
+fn strip_ansi_into(s: &str, out: &mut String) {
+    let mut rest = s;
+    while let Some(start) = rest.find(is_strippable) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = if rest.starts_with('\u{001B}') {
+            ansi_sequence_len(rest)
+        } else {
+            rest.chars().next().map_or(0, char::len_utf8)
+        };
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+}
+
+/// Remove only ANSI escape sequences from `s`, keeping other controls.
+fn strip_escapes(s: &str) -> Cow<'_, str> {
+    if !s.contains('\u{001B}') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('\u{001B}') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + ansi_sequence_len(&rest[start..])..];
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+fn is_strippable(c: char) -> bool {
+    c.is_control() && c != '\n'
+}
+
+/// Codepoints that attach to a preceding base character as part of its
+/// grapheme cluster (Grapheme_Cluster_Break=Extend: Grapheme_Extend plus the
+/// emoji skin-tone modifiers) but that `unicode-width` gives a nonzero width.
+/// All other Extend codepoints (combining marks, variation selectors, ...)
+/// are already measured as 0 by `unicode-width`.
+const EXTEND_NONZERO: &[(char, char)] = &[
+    ('\u{0897}', '\u{0897}'),
+    ('\u{1715}', '\u{1715}'),
+    ('\u{1734}', '\u{1734}'),
+    ('\u{1ACF}', '\u{1ADD}'),
+    ('\u{1AE0}', '\u{1AEB}'),
+    ('\u{1B44}', '\u{1B44}'),
+    ('\u{1BAA}', '\u{1BAA}'),
+    ('\u{1BF2}', '\u{1BF3}'),
+    ('\u{2D7F}', '\u{2D7F}'),
+    ('\u{A953}', '\u{A953}'),
+    ('\u{A9C0}', '\u{A9C0}'),
+    ('\u{10D69}', '\u{10D6D}'),
+    ('\u{10EFA}', '\u{10EFC}'),
+    ('\u{111C0}', '\u{111C0}'),
+    ('\u{11235}', '\u{11235}'),
+    ('\u{1134D}', '\u{1134D}'),
+    ('\u{113B8}', '\u{113B8}'),
+    ('\u{113BB}', '\u{113C0}'),
+    ('\u{113C2}', '\u{113C2}'),
+    ('\u{113C5}', '\u{113C5}'),
+    ('\u{113C7}', '\u{113C9}'),
+    ('\u{113CE}', '\u{113D0}'),
+    ('\u{113D2}', '\u{113D2}'),
+    ('\u{113E1}', '\u{113E2}'),
+    ('\u{116B6}', '\u{116B6}'),
+    ('\u{1193D}', '\u{1193D}'),
+    ('\u{11B60}', '\u{11B60}'),
+    ('\u{11B62}', '\u{11B64}'),
+    ('\u{11B66}', '\u{11B66}'),
+    ('\u{11F41}', '\u{11F41}'),
+    ('\u{11F5A}', '\u{11F5A}'),
+    ('\u{1611E}', '\u{16129}'),
+    ('\u{1612D}', '\u{1612F}'),
+    ('\u{16FF0}', '\u{16FF1}'),
+    ('\u{1D166}', '\u{1D166}'),
+    ('\u{1D16D}', '\u{1D16D}'),
+    ('\u{1E5EE}', '\u{1E5EF}'),
+    ('\u{1E6E3}', '\u{1E6E3}'),
+    ('\u{1E6E6}', '\u{1E6E6}'),
+    ('\u{1E6EE}', '\u{1E6EF}'),
+    ('\u{1E6F5}', '\u{1E6F5}'),
+    ('\u{1F3FB}', '\u{1F3FF}'),
+];
+
+/// Codepoints displayed as emoji by default (Emoji_Presentation), plus the
+/// regional indicators that pair up into flags. Text-default symbols such
+/// as `❤` count as emoji only when followed by VS16 (`U+FE0F`).
+const EMOJI_PRESENTATION: &[(char, char)] = &[
+    ('\u{231A}', '\u{231B}'),
+    ('\u{23E9}', '\u{23EC}'),
+    ('\u{23F0}', '\u{23F0}'),
+    ('\u{23F3}', '\u{23F3}'),
+    ('\u{25FD}', '\u{25FE}'),
+    ('\u{2614}', '\u{2615}'),
+    ('\u{2648}', '\u{2653}'),
+    ('\u{267F}', '\u{267F}'),
+    ('\u{2693}', '\u{2693}'),
+    ('\u{26A1}', '\u{26A1}'),
+    ('\u{26AA}', '\u{26AB}'),
+    ('\u{26BD}', '\u{26BE}'),
+    ('\u{26C4}', '\u{26C5}'),
+    ('\u{26CE}', '\u{26CE}'),
+    ('\u{26D4}', '\u{26D4}'),
+    ('\u{26EA}', '\u{26EA}'),
+    ('\u{26F2}', '\u{26F3}'),
+    ('\u{26F5}', '\u{26F5}'),
+    ('\u{26FA}', '\u{26FA}'),
+    ('\u{26FD}', '\u{26FD}'),
+    ('\u{2705}', '\u{2705}'),
+    ('\u{270A}', '\u{270B}'),
+    ('\u{2728}', '\u{2728}'),
+    ('\u{274C}', '\u{274C}'),
+    ('\u{274E}', '\u{274E}'),
+    ('\u{2753}', '\u{2755}'),
+    ('\u{2757}', '\u{2757}'),
+    ('\u{2795}', '\u{2797}'),
+    ('\u{27B0}', '\u{27B0}'),
+    ('\u{27BF}', '\u{27BF}'),
+    ('\u{2B1B}', '\u{2B1C}'),
+    ('\u{2B50}', '\u{2B50}'),
+    ('\u{2B55}', '\u{2B55}'),
+    ('\u{1F004}', '\u{1F004}'),
+    ('\u{1F0CF}', '\u{1F0CF}'),
+    ('\u{1F18E}', '\u{1F18E}'),
+    ('\u{1F191}', '\u{1F19A}'),
+    ('\u{1F1E6}', '\u{1F1FF}'),
+    ('\u{1F201}', '\u{1F201}'),
+    ('\u{1F21A}', '\u{1F21A}'),
+    ('\u{1F22F}', '\u{1F22F}'),
+    ('\u{1F232}', '\u{1F236}'),
+    ('\u{1F238}', '\u{1F23A}'),
+    ('\u{1F250}', '\u{1F251}'),
+    ('\u{1F300}', '\u{1F64F}'),
+    ('\u{1F680}', '\u{1F6FF}'),
+    ('\u{1F7E0}', '\u{1F7EB}'),
+    ('\u{1F90C}', '\u{1F9FF}'),
+    ('\u{1FA70}', '\u{1FAFF}'),
+];
+
+/// Check whether `c` falls in one of the sorted, non-overlapping `ranges`
+fn in_table(c: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if c < start {
+                std::cmp::Ordering::Greater
+            } else if c > end {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Whether a grapheme cluster is displayed as an emoji
+fn is_emoji(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|c| in_table(c, EMOJI_PRESENTATION))
+        || grapheme.contains('\u{FE0F}')
+}
+
+/// Whether `c` is a zero-width combining mark, one that joins the cluster
+/// of the character before it.
+///
+/// With no base to join (at the start of a string or line) such a mark forms
+/// a defective cluster, which terminals draw in a column of its own, often
+/// over a dotted circle. Joiners, variation selectors and tags are Extend
+/// codepoints too but stay invisible, so they are excluded.
+fn is_combining_mark(c: char) -> bool {
+    if matches!(
+        c,
+        '\u{200C}' | '\u{200D}' | '\u{180B}'..='\u{180F}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0000}'..='\u{E0FFF}'
+    ) || unicode_width::UnicodeWidthChar::width(c) != Some(0)
+    {
+        return false;
+    }
+
+    // `c` is a mark exactly when it clusters with a base letter
+    joins('a', c)
+}
+
+/// Whether `c` continues the grapheme cluster of a preceding `previous`
+fn joins(previous: char, c: char) -> bool {
+    let mut pair = [0; 8];
+    let len = previous.encode_utf8(&mut pair).len();
+    let len = len + c.encode_utf8(&mut pair[len..]).len();
+    std::str::from_utf8(&pair[..len]).is_ok_and(|pair| pair.graphemes(true).nth(1).is_none())
+}
+
+/// Sum the Unicode width of already-stripped text, treating control
+/// characters as width 0
+fn visible_width(clean_str: &str, opts: &WidthOptions) -> usize {
     clean_str
+        .graphemes(true)
+        .map(|grapheme| grapheme_width(grapheme, opts))
+        .sum()
+}
+
+/// Measure already-stripped text cluster by cluster, choosing a width for
+/// characters `unicode-width` can't measure.
+///
+/// Such characters (C0 and C1 controls) count `unknown_width` when they
+/// start a grapheme cluster, and nothing when attached inside one, so the
+/// `\r\n` cluster counts once. ANSI sequences are not stripped here: pass
+/// text that has already been cleaned, or use [`string_width`].
+///
+/// # Examples
+///
+/// ```
+/// use string_width::string_width_graphemes;
+///
+/// assert_eq!(string_width_graphemes("a\u{0085}b", 1), 3);
+/// assert_eq!(string_width_graphemes("a\u{0085}b", 0), 2);
+/// assert_eq!(string_width_graphemes("a\r\n", 1), 2);
+/// ```
+pub fn string_width_graphemes(s: &str, unknown_width: usize) -> usize {
+    let opts = WidthOptions::default();
+    s.graphemes(true)
+        .map(|grapheme| {
+            let base_unknown = grapheme
+                .chars()
+                .next()
+                .is_some_and(|base| unicode_width::UnicodeWidthChar::width(base).is_none());
+            grapheme_width(grapheme, &opts) + if base_unknown { unknown_width } else { 0 }
+        })
+        .sum()
+}
+
+/// Measure a single grapheme cluster
+fn grapheme_width(grapheme: &str, opts: &WidthOptions) -> usize {
+    if let Some(emoji_width) = opts.emoji_width
+        && is_emoji(grapheme)
+    {
+        return emoji_width;
+    }
+
+    grapheme
         .chars()
-        .map(|c| {
+        .enumerate()
+        .map(|(i, c)| {
+            if let Some(&width) = opts.overrides.get(&c) {
+                return width;
+            }
+            // Extend codepoints contribute nothing once attached to a base
             if c.is_control() {
+                if c == '\n' { 0 } else { opts.control_width }
+            } else if i > 0 && in_table(c, EXTEND_NONZERO) {
                 0
+            } else if i == 0 && is_combining_mark(c) {
+                1
             } else {
                 unicode_width::UnicodeWidthChar::width(c).unwrap_or(0)
             }
@@ -36,6 +606,12 @@ pub fn string_width(s: &str) -> usize {
 /// This function splits the input string by newlines and returns the width
 /// of the line that has the greatest display width.
 /// 
+/// An empty string and a string of blank lines both measure 0; use
+/// [`line_count_and_widest`] when the number of lines matters too.
+///
+/// Lines are measured in place as [`string_width_no_alloc`] does, so no
+/// stripped copy is made of lines carrying escape sequences.
+/// 
 /// # Examples
 /// 
 /// ```
@@ -46,12 +622,440 @@ pub fn string_width(s: &str) -> usize {
 /// assert_eq!(widest_line("古\n古古古"), 6);
 /// ```
 pub fn widest_line(s: &str) -> usize {
+    // Single-line input is by far the common case; skip the line iterator
+    if !s.contains('\n') {
+        return string_width_no_alloc(s);
+    }
+
     s.lines()
-        .map(string_width)
+        .map(string_width_no_alloc)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Find the width of the widest line using custom measuring options.
+///
+/// Lines break at `\n`, and also at `\u{2028}` and `\u{2029}` when
+/// [`WidthOptions::unicode_line_breaks`] is set.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::{widest_line_with, WidthOptions};
+///
+/// let opts = WidthOptions::new().unicode_line_breaks(true);
+/// assert_eq!(widest_line_with("abc\u{2028}de", &opts), 3);
+/// assert_eq!(widest_line_with("abc\u{2028}de", &WidthOptions::default()), 6);
+/// ```
+pub fn widest_line_with(s: &str, opts: &WidthOptions) -> usize {
+    s.split(|c| is_line_break(c, opts.unicode_line_breaks))
+        .map(|line| measure(line, opts))
         .max()
         .unwrap_or(0)
 }
 
+/// Whether `c` ends a line, counting the Unicode line and paragraph
+/// separators only when `unicode_line_breaks` is set.
+pub fn is_line_break(c: char, unicode_line_breaks: bool) -> bool {
+    c == '\n' || (unicode_line_breaks && matches!(c, '\u{2028}' | '\u{2029}'))
+}
+
+/// The byte length of the ANSI escape sequence at the start of `s`, which
+/// must begin with ESC.
+///
+/// CSI sequences run to their final byte; OSC, DCS, APC and PM strings run
+/// to BEL or ST. Sequences with intermediate bytes, like the charset switch
+/// `ESC(B`, run to the byte after them, and any other escape (such as the
+/// cursor save `ESC7`) is two characters long.
+///
+/// Malformed input is cut short so it can't hide visible text:
+///
+/// - A CSI sequence ends just before the first byte that can't belong to
+///   it (a control character or anything outside ASCII), so in
+///   `"ESC[12;古"` the `古` is kept. Note that any ASCII letter is a valid
+///   final byte, so `"ESC[hello"` is the complete sequence `ESC[h` followed
+///   by `"ello"`.
+/// - An OSC, DCS, APC or PM string missing its terminator ends before the
+///   next `\n` rather than consuming the following lines.
+/// - Otherwise, a sequence that reaches the end of `s` extends to it.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::ansi_sequence_len;
+///
+/// assert_eq!(ansi_sequence_len("\u{001B}[31mred"), 5);
+/// assert_eq!(ansi_sequence_len("\u{001B}]8;;x\u{0007}link"), 7);
+/// assert_eq!(ansi_sequence_len("\u{001B}[12;古"), 5);
+/// assert_eq!(ansi_sequence_len("\u{001B}]8;;x\nnext"), 6);
+/// ```
+pub fn ansi_sequence_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    match bytes.get(1) {
+        Some(b'[') => match bytes[2..].iter().position(|b| !(0x20..=0x3F).contains(b)) {
+            Some(end) if (0x40..=0x7E).contains(&bytes[end + 2]) => end + 3,
+            Some(end) => end + 2,
+            None => bytes.len(),
+        },
+        Some(b']' | b'P' | b'_' | b'^') => {
+            let mut i = 2;
+            while i < bytes.len() {
+                if bytes[i] == 0x07 {
+                    return i + 1;
+                }
+                if bytes[i] == b'\n' {
+                    return i;
+                }
+                if bytes[i] == 0x1B && bytes.get(i + 1) == Some(&b'\\') {
+                    return i + 2;
+                }
+                i += 1;
+            }
+            bytes.len()
+        }
+        // nF sequences such as the charset switch `ESC(B`: intermediate
+        // bytes, then one final byte
+        Some(0x20..=0x2F) => {
+            let intermediates = bytes[1..].iter().take_while(|b| (0x20..=0x2F).contains(*b)).count();
+            match bytes.get(1 + intermediates) {
+                Some(0x30..=0x7E) => intermediates + 2,
+                _ => 1 + intermediates,
+            }
+        }
+        Some(_) => 1 + s[1..].chars().next().map_or(0, char::len_utf8),
+        None => 1,
+    }
+}
+
+/// Return the number of lines in a string along with the width of the
+/// widest one.
+///
+/// Lines are separated by `\n`, so a trailing newline starts one more
+/// (empty) line, matching how a box renders it. Only the empty string has
+/// no lines at all.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::line_count_and_widest;
+///
+/// assert_eq!(line_count_and_widest(""), (0, 0));
+/// assert_eq!(line_count_and_widest("\n"), (2, 0));
+/// assert_eq!(line_count_and_widest("古\nabc"), (2, 3));
+/// ```
+pub fn line_count_and_widest(s: &str) -> (usize, usize) {
+    if s.is_empty() {
+        return (0, 0);
+    }
+
+    s.split('\n')
+        .fold((0, 0), |(count, widest), line| (count + 1, widest.max(string_width_no_alloc(line))))
+}
+
+/// Replace each tab with spaces up to the next tab stop.
+///
+/// Stops fall every `tab_width` columns, counted from the start of each
+/// line by display width, so wide characters and ANSI escape sequences
+/// before a tab are accounted for. A `tab_width` of 0 leaves tabs as they
+/// are.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::expand_tabs;
+///
+/// assert_eq!(expand_tabs("a\tb", 4), "a   b");
+/// assert_eq!(expand_tabs("古\tb\nabcd\tc", 4), "古  b\nabcd    c");
+/// ```
+pub fn expand_tabs(s: &str, tab_width: usize) -> String {
+    if tab_width == 0 {
+        return s.to_string();
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut column = 0;
+    for (index, segment) in s.split('\t').enumerate() {
+        if index > 0 {
+            let pad = tab_width - column % tab_width;
+            out.extend(std::iter::repeat_n(' ', pad));
+            column += pad;
+        }
+        out.push_str(segment);
+        column = match segment.rfind('\n') {
+            Some(newline) => string_width(&segment[newline + 1..]),
+            None => column + string_width(segment),
+        };
+    }
+    out
+}
+
+/// Truncate a string so that its display width does not exceed `max_width`.
+///
+/// ANSI escape sequences are removed and the remaining text is cut on
+/// grapheme cluster boundaries, so a multi-codepoint cluster such as a ZWJ
+/// emoji sequence is either kept whole or dropped whole, never split.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::truncate;
+///
+/// assert_eq!(truncate("hello", 3), "hel");
+/// assert_eq!(truncate("古古古", 5), "古古");
+/// assert_eq!(truncate("\u{001B}[1mhello\u{001B}[22m", 2), "he");
+/// ```
+pub fn truncate(s: &str, max_width: usize) -> String {
+    let clean_str = strip_ansi(s);
+
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in clean_str.graphemes(true) {
+        let width = string_width(grapheme);
+        if used + width > max_width {
+            break;
+        }
+        used += width;
+        out.push_str(grapheme);
+    }
+    out
+}
+
+/// Truncate like [`truncate`], but keep the ANSI escape sequences that
+/// precede the cut.
+///
+/// Styling set before the cut survives even when it opened several
+/// characters earlier, and if any SGR style is still open at the cut a reset
+/// (`ESC[0m`) is appended so it can't bleed into whatever follows. Sequences
+/// after the cut are dropped. A string that already fits is returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::truncate_ansi;
+///
+/// assert_eq!(
+///     truncate_ansi("ab\u{001B}[31mcdef\u{001B}[0m", 4),
+///     "ab\u{001B}[31mcd\u{001B}[0m"
+/// );
+/// ```
+pub fn truncate_ansi(s: &str, max_width: usize) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending = String::new();
+    let mut styled = false;
+    let mut used = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        if rest.starts_with('\u{001B}') {
+            let len = ansi_sequence_len(rest);
+            pending.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+
+        let end = rest.find('\u{001B}').unwrap_or(rest.len());
+        for grapheme in rest[..end].graphemes(true) {
+            let width = string_width(grapheme);
+            if used + width > max_width {
+                if styled {
+                    out.push_str("\u{001B}[0m");
+                }
+                return out;
+            }
+            styled = sgr_state_after(styled, &pending);
+            out.push_str(&pending);
+            pending.clear();
+            used += width;
+            out.push_str(grapheme);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(&pending);
+    out
+}
+
+/// The visible part of a single line spanning columns `[start_col, end_col)`.
+///
+/// A wide character that would straddle either edge is left out rather than
+/// split, so the result may be narrower than the range. SGR styling opened
+/// before `start_col` is replayed at the front of the slice, and a reset
+/// (`ESC[0m`) is appended if a style is still open at the end. This is the
+/// building block for scrolling content horizontally.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::slice_by_width;
+///
+/// assert_eq!(slice_by_width("hello world", 6, 11), "world");
+/// assert_eq!(slice_by_width("a古b", 2, 4), "b");
+/// assert_eq!(
+///     slice_by_width("\u{001B}[31mred\u{001B}[0m", 1, 3),
+///     "\u{001B}[31med\u{001B}[0m"
+/// );
+/// ```
+pub fn slice_by_width(s: &str, start_col: usize, end_col: usize) -> String {
+    let mut out = String::new();
+    let mut pending = String::new();
+    let mut styled = false;
+    let mut col = 0;
+    let mut rest = s;
+    'scan: while !rest.is_empty() {
+        if rest.starts_with('\u{001B}') {
+            let len = ansi_sequence_len(rest);
+            let escape = &rest[..len];
+            if !out.is_empty() {
+                pending.push_str(escape);
+            } else if escape.starts_with("\u{001B}[") && escape.ends_with('m') {
+                // Until the slice starts only styling matters, and a reset
+                // cancels everything collected so far
+                if matches!(escape, "\u{001B}[0m" | "\u{001B}[m") {
+                    pending.clear();
+                } else {
+                    pending.push_str(escape);
+                }
+            }
+            rest = &rest[len..];
+            continue;
+        }
+
+        let end = rest.find('\u{001B}').unwrap_or(rest.len());
+        for grapheme in rest[..end].graphemes(true) {
+            if col >= end_col {
+                break 'scan;
+            }
+            let width = string_width(grapheme);
+            if col >= start_col && col + width <= end_col {
+                styled = sgr_state_after(styled, &pending);
+                out.push_str(&pending);
+                pending.clear();
+                out.push_str(grapheme);
+            }
+            col += width;
+        }
+        rest = &rest[end..];
+    }
+    if styled {
+        out.push_str("\u{001B}[0m");
+    }
+    out
+}
+
+/// Whether an SGR style is open after the escape sequences in `escapes`,
+/// given whether one was open before them.
+fn sgr_state_after(mut styled: bool, escapes: &str) -> bool {
+    let mut rest = escapes;
+    while !rest.is_empty() {
+        let len = ansi_sequence_len(rest);
+        let escape = &rest[..len];
+        if escape.starts_with("\u{001B}[") && escape.ends_with('m') {
+            styled = !matches!(escape, "\u{001B}[0m" | "\u{001B}[m");
+        }
+        rest = &rest[len..];
+    }
+    styled
+}
+
+/// A memo of measured line widths, for callers that re-measure the same
+/// content repeatedly (e.g. re-rendering an unchanged box).
+///
+/// By default the cache is unbounded. Use [`WidthCache::with_capacity`] to cap
+/// the number of entries; once full, the least recently used line is evicted.
+///
+/// # Examples
+///
+/// ```
+/// use string_width::WidthCache;
+///
+/// let mut cache = WidthCache::with_capacity(2);
+/// assert_eq!(cache.width("古"), 2);
+/// assert_eq!(cache.width("hello"), 5);
+/// assert_eq!(cache.width("hi"), 2);
+/// assert_eq!(cache.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WidthCache {
+    capacity: Option<usize>,
+    entries: HashMap<String, (usize, u64)>,
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    hits: u64,
+}
+
+impl WidthCache {
+    /// Create an unbounded cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache holding at most `capacity` lines
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Return the display width of `s`, measuring it only if it isn't cached
+    pub fn width(&mut self, s: &str) -> usize {
+        self.tick += 1;
+        let tick = self.tick;
+
+        if let Some((width, last_used)) = self.entries.get_mut(s) {
+            self.recency.remove(last_used);
+            self.recency.insert(tick, s.to_string());
+            *last_used = tick;
+            self.hits += 1;
+            return *width;
+        }
+
+        let width = string_width(s);
+        self.entries.insert(s.to_string(), (width, tick));
+        self.recency.insert(tick, s.to_string());
+
+        if let Some(capacity) = self.capacity {
+            while self.entries.len() > capacity {
+                match self.recency.pop_first() {
+                    Some((_, oldest)) => self.entries.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+
+        width
+    }
+
+    /// The maximum number of cached lines, if bounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// The number of cached lines
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no lines
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `s` currently has a cached width
+    pub fn contains(&self, s: &str) -> bool {
+        self.entries.contains_key(s)
+    }
+
+    /// How many lookups were answered without measuring, since creation
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Drop all cached widths
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,4 +1140,595 @@ mod tests {
         // Lines with different types of content
         assert_eq!(widest_line("ascii\n古文字\n\u{001B}[32mcolored\u{001B}[0m"), 7);
     }
+
+    #[test]
+    fn test_widest_line_ignores_raw_escape_length() {
+        // The first line has by far the most bytes but only 3 visible columns
+        let styled = "\u{001B}[1m\u{001B}[4m\u{001B}[38;2;255;128;0mabc\u{001B}[0m\u{001B}[49m";
+        let text = format!("{}\nhello", styled);
+        assert!(styled.len() > "hello".len());
+        assert_eq!(widest_line(&text), 5);
+        assert_eq!(widest_line(&format!("hello\n{}", styled)), 5);
+    }
+
+    #[test]
+    fn test_widest_line_single_line_matches_string_width() {
+        for line in ["", "hello", "古文字", "\u{001B}[1mbold\u{001B}[0m", "a\u{0008}b", "👨\u{200D}👩\u{200D}👧", "trailing\r"] {
+            assert_eq!(widest_line(line), string_width(line), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_width_cache_unbounded() {
+        let mut cache = WidthCache::new();
+        assert!(cache.is_empty());
+        assert_eq!(cache.width("hello"), 5);
+        assert_eq!(cache.width("古古"), 4);
+        assert_eq!(cache.width("hello"), 5);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.capacity(), None);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_width_cache_bounded_eviction() {
+        let mut cache = WidthCache::with_capacity(3);
+        for i in 0..10 {
+            let line = "x".repeat(i + 1);
+            assert_eq!(cache.width(&line), i + 1);
+            assert!(cache.len() <= 3);
+        }
+        assert_eq!(cache.len(), 3);
+
+        // The earliest lines were evicted, but re-measure correctly
+        assert!(!cache.contains("x"));
+        assert_eq!(cache.width("x"), 1);
+        assert!(cache.contains("x"));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_width_cache_evicts_least_recently_used() {
+        let mut cache = WidthCache::with_capacity(2);
+        cache.width("a");
+        cache.width("bb");
+        // Touch "a" so "bb" becomes the least recently used
+        cache.width("a");
+        cache.width("\u{001B}[1mccc\u{001B}[0m");
+
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("bb"));
+        assert_eq!(cache.width("\u{001B}[1mccc\u{001B}[0m"), 3);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("", 3), "");
+
+        // A wide character that doesn't fit is dropped, not split
+        assert_eq!(truncate("a古b", 2), "a");
+        assert_eq!(truncate("a古b", 3), "a古");
+
+        // Combining marks stay with their base
+        assert_eq!(truncate("e\u{0301}x", 1), "e\u{0301}");
+    }
+
+    #[test]
+    fn test_truncate_ansi_keeps_open_color() {
+        // The color opens three characters before the cut and must survive
+        let text = "plain \u{001B}[32mgreen text\u{001B}[0m";
+        assert_eq!(truncate_ansi(text, 9), "plain \u{001B}[32mgre\u{001B}[0m");
+        assert_eq!(string_width(&truncate_ansi(text, 9)), 9);
+
+        // Nothing is open at the cut, so no reset is added
+        assert_eq!(truncate_ansi("\u{001B}[1mab\u{001B}[0mcd", 3), "\u{001B}[1mab\u{001B}[0mc");
+
+        // Sequences after the cut are dropped; a fitting string is untouched
+        assert_eq!(truncate_ansi("ab\u{001B}[31mcd", 2), "ab");
+        assert_eq!(truncate_ansi(text, 100), text);
+    }
+
+    #[test]
+    fn test_truncate_zwj_sequence() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let text = format!("hi{}", family);
+
+        // A budget that would fit only part of the family drops it entirely
+        assert_eq!(truncate(&text, 4), "hi");
+        assert_eq!(truncate(&text, 7), "hi");
+
+        // A budget covering the whole cluster keeps it intact
+        assert_eq!(truncate(&text, string_width(&text)), text);
+    }
+
+    #[test]
+    fn test_string_width_batch() {
+        let lines = [
+            "",
+            "hello",
+            "古古",
+            "\u{001B}[1m古\u{001B}[22m",
+            "hello\tworld",
+            "👨\u{200D}👩",
+            "\u{001B}[31mred\u{001B}[0m and plain",
+            "ab\u{0008}c",
+        ];
+        let expected: Vec<usize> = lines.iter().map(|line| string_width(line)).collect();
+        assert_eq!(string_width_batch(&lines), expected);
+        assert!(string_width_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_grapheme_extend_attached() {
+        // Variation selector and ZWJ add nothing after a base
+        assert_eq!(string_width("\u{2764}\u{FE0F}"), string_width("\u{2764}"));
+        assert_eq!(string_width("a\u{FE0E}"), 1);
+        assert_eq!(string_width("a\u{200D}"), 1);
+
+        // Skin-tone modifier attaches to the emoji it follows
+        assert_eq!(string_width("👍\u{1F3FD}"), 2);
+        assert_eq!(string_width("x👍\u{1F3FD}y"), 4);
+
+        // Spacing virama attaches to its consonant
+        assert_eq!(string_width("\u{1B13}\u{1B44}"), string_width("\u{1B13}"));
+    }
+
+    #[test]
+    fn test_grapheme_extend_standalone() {
+        // With no base to attach to, a modifier keeps its own width
+        assert_eq!(string_width("\u{1F3FD}"), 2);
+        assert_eq!(string_width("\u{FE0F}"), 0);
+    }
+
+    #[test]
+    fn test_tables_sorted() {
+        for table in [EXTEND_NONZERO, EMOJI_PRESENTATION] {
+            assert!(table.windows(2).all(|pair| pair[0].1 < pair[1].0));
+            assert!(table.iter().all(|&(start, end)| start <= end));
+        }
+    }
+
+    #[test]
+    fn test_emoji_width_forced() {
+        let two = WidthOptions::new().emoji_width(2);
+        let one = WidthOptions::new().emoji_width(1);
+
+        assert_eq!(string_width_with("😀", &two), 2);
+        assert_eq!(string_width_with("😀", &one), 1);
+        assert_eq!(string_width_with("a😀b", &one), 3);
+
+        // Text-default symbols only count as emoji with VS16
+        assert_eq!(string_width_with("\u{2764}", &two), 1);
+        assert_eq!(string_width_with("\u{2764}\u{FE0F}", &two), 2);
+
+        // A whole ZWJ sequence or flag is one emoji
+        assert_eq!(string_width_with("👨\u{200D}👩\u{200D}👧", &two), 2);
+        assert_eq!(string_width_with("\u{1F1FA}\u{1F1F8}", &one), 1);
+
+        // Non-emoji text is unaffected
+        assert_eq!(string_width_with("古a", &one), 3);
+    }
+
+    #[test]
+    fn test_default_width_options_match_string_width() {
+        let opts = WidthOptions::default();
+        for s in ["", "hello", "古", "😀", "\u{001B}[1m古\u{001B}[22m", "👨\u{200D}👩"] {
+            assert_eq!(string_width_with(s, &opts), string_width(s));
+        }
+    }
+
+    #[test]
+    fn test_backspace() {
+        // The `c` overwrites the `b`
+        assert_eq!(string_width("ab\u{0008}c"), 2);
+        assert_eq!(string_width("ab\u{0008}\u{0008}xyz"), 3);
+
+        // Backing up doesn't hide what was already drawn
+        assert_eq!(string_width("abc\u{0008}"), 3);
+
+        // The column never goes below zero
+        assert_eq!(string_width("\u{0008}\u{0008}a"), 1);
+        assert_eq!(string_width("古\u{0008}x"), 2);
+    }
+
+    #[test]
+    fn test_raw_and_visible_width() {
+        let s = "\u{001B}[32m古文字\u{001B}[39m";
+        assert_eq!(raw_and_visible_width(s), (19, 6));
+        assert_eq!(raw_and_visible_width(""), (0, 0));
+    }
+
+    #[test]
+    fn test_width_strategies() {
+        let samples = ["hello", "古文字", "\u{001B}[31mred\u{001B}[0m", "hi😀"];
+        for s in samples {
+            assert_eq!(UnicodeWidth.width(s), string_width(s));
+            assert_eq!(WidthOptions::default().width(s), string_width(s));
+        }
+        assert_eq!(WidthOptions::new().emoji_width(1).width("hi😀"), 3);
+    }
+
+    #[test]
+    fn test_replacement_character_from_lossy_decode() {
+        let decoded = String::from_utf8_lossy(&[b'a', 0xFF, b'b']);
+        assert_eq!(decoded, "a\u{FFFD}b");
+        assert_eq!(string_width(&decoded), 3);
+        assert_eq!(string_width("\u{FFFD}"), 1);
+        assert_eq!(string_width_batch(&[&decoded]), vec![3]);
+    }
+
+    #[test]
+    fn test_string_width_graphemes_unknown_width() {
+        // A standalone control counts as the configured width
+        assert_eq!(string_width_graphemes("\u{0085}", 2), 2);
+        assert_eq!(string_width_graphemes("x\u{0001}y", 1), 3);
+
+        // Attached inside a cluster it contributes nothing: "\r\n" is one
+        // cluster with an unknown base, while "\n\r" is two
+        assert_eq!(string_width_graphemes("\r\n", 1), 1);
+        assert_eq!(string_width_graphemes("\n\r", 1), 2);
+
+        // Measurable text is unaffected
+        assert_eq!(string_width_graphemes("古e\u{0301}", 5), 3);
+    }
+
+    #[test]
+    fn test_hangul_syllables_and_jamo() {
+        // Precomposed syllable blocks are wide
+        assert_eq!(string_width("한"), 2);
+        assert_eq!(string_width("한국어"), 6);
+
+        // A decomposed leading + vowel + trailing jamo sequence is one
+        // cluster as wide as the syllable it spells
+        let decomposed = "\u{1112}\u{1161}\u{11AB}";
+        assert_eq!(decomposed.graphemes(true).count(), 1);
+        assert_eq!(string_width(decomposed), 2);
+        assert_eq!(string_width(&format!("{}국", decomposed)), 4);
+
+        // Halfwidth Hangul letters are narrow
+        assert_eq!(string_width("\u{FFA1}\u{FFC2}"), 2);
+    }
+
+    #[test]
+    fn test_width_overrides() {
+        let opts = WidthOptions::new().override_width('\u{1161}', 1);
+        assert_eq!(string_width_with("\u{1112}\u{1161}", &opts), 3);
+        assert_eq!(string_width_with("a", &opts), 1);
+
+        let opts = WidthOptions::new().override_width('→', 2);
+        assert_eq!(string_width_with("a→b", &opts), 4);
+        assert_eq!(string_width("a→b"), 3);
+    }
+
+    #[test]
+    fn test_line_count_and_widest() {
+        assert_eq!(line_count_and_widest(""), (0, 0));
+        assert_eq!(line_count_and_widest("\n"), (2, 0));
+        assert_eq!(line_count_and_widest("a\n"), (2, 1));
+        assert_eq!(line_count_and_widest("hello\n\u{001B}[1m古古古\u{001B}[0m\n"), (3, 6));
+
+        // widest_line agrees on the width but can't tell these apart
+        assert_eq!(widest_line(""), widest_line("\n"));
+    }
+
+    #[test]
+    fn test_unterminated_escape_sequences() {
+        // `h` is a valid CSI final byte, so only `ESC[h` is an escape
+        assert_eq!(string_width("\u{001B}[hello"), 4);
+        assert_eq!(string_width("ab\u{001B}["), 2);
+        assert_eq!(string_width("ab\u{001B}[12;"), 2);
+        assert_eq!(string_width("ab\u{001B}"), 2);
+
+        // A malformed CSI stops before text that can't belong to it
+        assert_eq!(string_width("ab\u{001B}[12;古古"), 6);
+        assert_eq!(string_width("\u{001B}[1;\u{0007}ok"), 2);
+
+        // An unterminated OSC hyperlink doesn't swallow the following lines
+        assert_eq!(
+            line_count_and_widest("ab\u{001B}]8;;http://x\nnext"),
+            (2, 4)
+        );
+
+        let lines = ["\u{001B}[hello", "ab\u{001B}[12;古古"];
+        assert_eq!(string_width_batch(&lines), vec![4, 6]);
+        assert_eq!(truncate("ab\u{001B}[12;古古", 4), "ab古");
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("\tx", 4), "    x");
+        assert_eq!(expand_tabs("abc\t\tx", 4), "abc     x");
+        assert_eq!(expand_tabs("\u{001B}[1mab\u{001B}[22m\tx", 4), "\u{001B}[1mab\u{001B}[22m  x");
+        assert_eq!(expand_tabs("a\tb", 0), "a\tb");
+        assert_eq!(expand_tabs("no tabs", 8), "no tabs");
+    }
+
+    #[test]
+    fn test_unicode_line_separators() {
+        let text = "first line\u{2028}second\u{2029}third";
+        let opts = WidthOptions::new().unicode_line_breaks(true);
+        assert_eq!(widest_line_with(text, &opts), 10);
+        assert_eq!(widest_line_with("ab\u{2028}古古古", &opts), 6);
+
+        // Off by default: the separators stay inside one line
+        assert_eq!(widest_line(text), widest_line_with(text, &WidthOptions::default()));
+        assert_eq!(widest_line_with(text, &WidthOptions::default()), string_width(text));
+        assert!(is_line_break('\n', false));
+        assert!(!is_line_break('\u{2028}', false));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Pieces that stress the additive parts of measuring: wide and
+        /// zero-width characters, escapes, backspaces and tabs
+        const TOKENS: &[&str] = &[
+            "a",
+            "古",
+            "😀",
+            "👨\u{200D}👩\u{200D}👧",
+            "e\u{0301}",
+            "\u{200B}",
+            "\u{001B}[31m",
+            "\u{001B}[",
+            "\u{001B}]8;;x\u{0007}",
+            "\u{0008}",
+            "\t",
+            "\n",
+        ];
+
+        fn text() -> impl Strategy<Value = String> {
+            prop_oneof![
+                any::<String>(),
+                prop::collection::vec(prop::sample::select(TOKENS), 0..12)
+                    .prop_map(|tokens| tokens.concat()),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn test_width_is_at_most_two_per_char(s in text()) {
+                let bound = s.chars().count() * 2;
+                prop_assert!(string_width(&s) <= bound);
+                prop_assert!(widest_line(&s) <= bound);
+                prop_assert!(string_width_batch(&[s.as_str()])[0] <= bound);
+            }
+
+            #[test]
+            fn test_no_alloc_agrees(s in text()) {
+                prop_assert_eq!(string_width_no_alloc(&s), string_width(&s));
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_width_no_alloc_matches_string_width() {
+        let corpus = [
+            "",
+            "a",
+            "hello",
+            "hello\nworld",
+            "hello\tworld",
+            "hello\u{200B}world",
+            "古",
+            "中文",
+            "こんにちは",
+            "한국어",
+            "\u{FFA1}\u{FFC2}",
+            "é",
+            "e\u{0301}",
+            "\u{1B13}\u{1B44}",
+            "a\u{200D}b",
+            "a\u{FE0E}",
+            "\u{2764}\u{FE0F}",
+            "👍\u{1F3FD}",
+            "x👍\u{1F3FD}y",
+            "👨\u{200D}👩\u{200D}👧\u{200D}👦",
+            "\u{001B}[1m古\u{001B}[22m",
+            "\u{001B}[1mhello古\u{001B}[0mworld",
+            "\u{001B}]8;;http://x\u{0007}link\u{001B}]8;;\u{0007}",
+            "\u{001B}[hello",
+            "ab\u{001B}[12;古古",
+            "\u{001B}[1;\u{0007}ok",
+            "ab\u{0008}\u{0008}xyz",
+            "古\u{0008}x",
+            "\u{0008}\u{0008}a",
+            "\u{0008}\u{1B44}",
+            "a\u{001B}[1m\u{1B44}",
+            "a\u{0007}\u{1B44}",
+            "\n\u{1B44}",
+            "\u{200B}\u{1B44}",
+            "\u{200B}\u{001B}[e\u{0301}",
+        ];
+        for s in corpus {
+            assert_eq!(string_width_no_alloc(s), string_width(s), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_leading_combining_mark() {
+        // With no base the acute accent takes a column of its own...
+        assert_eq!(string_width("\u{0301}"), 1);
+        assert_eq!(string_width("\u{0301}e"), 2);
+        assert_eq!(string_width("ab\n\u{0301}"), 3);
+        // ...but after a base it adds nothing
+        assert_eq!(string_width("e\u{0301}"), 1);
+        assert_eq!(string_width("\u{001B}[1me\u{001B}[22m\u{0301}"), 1);
+
+        // Invisible Extend codepoints stay zero width
+        assert_eq!(string_width("\u{200D}"), 0);
+        assert_eq!(string_width("\u{FE0F}"), 0);
+        assert_eq!(string_width_no_alloc("\u{0301}"), 1);
+    }
+
+    #[test]
+    fn test_less_common_escape_sequences() {
+        let cases = [
+            // Cursor save and restore
+            ("\u{001B}7hello\u{001B}8", 5),
+            // Private modes: hide and show the cursor
+            ("\u{001B}[?25lhello\u{001B}[?25h", 5),
+            // A device control string, terminated by ST
+            ("ab\u{001B}Pq#0;2;0;0;0\u{001B}\\cd", 4),
+            // Charset switches, as emitted by `tput sgr0`
+            ("\u{001B}(B\u{001B}[mhi\u{001B})0", 2),
+            ("古\u{001B}7\u{001B}[?1049h古\u{001B}8", 4),
+        ];
+        for (s, width) in cases {
+            assert_eq!(string_width(s), width, "{:?}", s);
+            assert_eq!(string_width_no_alloc(s), width, "{:?}", s);
+            assert_eq!(string_width_batch(&[s]), vec![width], "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_control_width() {
+        let s = "a\u{0001}b\u{007F}\u{0085}\u{001B}[31mc\u{001B}[0m";
+        assert_eq!(string_width(s), 3);
+        assert_eq!(string_width_with(s, &WidthOptions::new().control_width(2)), 9);
+
+        // Backspace and tab are drawn like any other control, but the
+        // newline still isn't
+        let opts = WidthOptions::new().control_width(2);
+        assert_eq!(string_width_with("ab\u{0008}c", &opts), 5);
+        assert_eq!(string_width_with("a\tb", &opts), 4);
+        assert_eq!(string_width_with("a\r\nb", &opts), 4);
+        assert_eq!(widest_line_with("\u{0002}\u{0003}\nabc", &opts), 4);
+    }
+
+    #[test]
+    fn test_slice_by_width_ascii() {
+        assert_eq!(slice_by_width("hello world", 0, 5), "hello");
+        assert_eq!(slice_by_width("hello world", 3, 8), "lo wo");
+        assert_eq!(slice_by_width("hello", 3, 100), "lo");
+        assert_eq!(slice_by_width("hello", 5, 10), "");
+        assert_eq!(slice_by_width("hello", 3, 3), "");
+        assert_eq!(slice_by_width("hello", 4, 2), "");
+    }
+
+    #[test]
+    fn test_slice_by_width_wide_edges() {
+        // 古 covers columns 1..3 and 今 covers 3..5
+        let text = "a古今b";
+        assert_eq!(slice_by_width(text, 1, 5), "古今");
+        assert_eq!(slice_by_width(text, 2, 5), "今");
+        assert_eq!(slice_by_width(text, 1, 4), "古");
+        assert_eq!(slice_by_width(text, 2, 4), "");
+        assert_eq!(slice_by_width(text, 0, 6), text);
+    }
+
+    #[test]
+    fn test_slice_by_width_colored() {
+        // The color opened before the slice is carried into it
+        let text = "ab\u{001B}[32mcdef\u{001B}[0mgh";
+        assert_eq!(slice_by_width(text, 3, 5), "\u{001B}[32mde\u{001B}[0m");
+        assert_eq!(slice_by_width(text, 4, 8), "\u{001B}[32mef\u{001B}[0mgh");
+
+        // Styling closed before the slice is not replayed
+        assert_eq!(slice_by_width(text, 6, 8), "gh");
+    }
+
+    #[test]
+    fn test_widest_line_ignores_hyperlink_urls() {
+        let url = format!("https://example.com/{}", "x".repeat(60));
+        let bel = format!("\u{001B}]8;;{}\u{0007}docs\u{001B}]8;;\u{0007}", url);
+        let st = format!("\u{001B}]8;;{}\u{001B}\\docs\u{001B}]8;;\u{001B}\\", url);
+        for link in [bel, st] {
+            // Only the four columns of link text count, so the plain line wins
+            let text = format!("see {}\nthe plain line", link);
+            assert_eq!(widest_line(&text), 14);
+            assert_eq!(widest_line_with(&text, &WidthOptions::default()), 14);
+            assert_eq!(line_count_and_widest(&text), (2, 14));
+            assert_eq!(widest_line(&link), 4);
+        }
+    }
+
+    #[test]
+    fn test_widest_line_matches_per_line_string_width() {
+        let corpus = [
+            "",
+            "\n\n",
+            "hello\nworld!",
+            "\u{001B}[31mred\u{001B}[0m\nplain",
+            "古古\n\u{001B}]8;;http://x\u{0007}link\u{001B}]8;;\u{0007}",
+            "e\u{0301}\u{0301}\n\u{200B}x\r\n👨\u{200D}👩\u{200D}👧",
+            "ab\u{0008}c\n\u{001B}[hello",
+        ];
+        for text in corpus {
+            let expected = text.lines().map(string_width).max().unwrap_or(0);
+            assert_eq!(widest_line(text), expected, "{:?}", text);
+            assert_eq!(line_count_and_widest(text).1, expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_enclosing_marks() {
+        // Enclosing marks wrap the previous character and add nothing
+        for mark in ['\u{20DD}', '\u{20DE}', '\u{20E3}'] {
+            let text = format!("A{}", mark);
+            assert_eq!(string_width(&text), 1);
+            assert_eq!(string_width_no_alloc(&text), 1);
+        }
+        assert_eq!(string_width("1\u{FE0F}\u{20E3}"), 1);
+
+        // Terminals that draw them wider can say so
+        let opts = WidthOptions::new().override_width('\u{20DD}', 1);
+        assert_eq!(string_width_with("A\u{20DD}", &opts), 2);
+    }
+
+    #[test]
+    fn test_half_and_full_width_forms() {
+        // Half-width katakana take one column each
+        let kana = "ｱｲｳｴｵｶｷｸｰ";
+        assert_eq!(string_width(kana), kana.chars().count());
+
+        // `unicode-width` treats the half-width sound marks as combining,
+        // while terminals give them a column of their own
+        assert_eq!(string_width("ﾊﾟｰﾄ"), 3);
+        let opts = WidthOptions::new()
+            .override_width('\u{FF9E}', 1)
+            .override_width('\u{FF9F}', 1);
+        assert_eq!(string_width_with("ﾊﾟｰﾄ", &opts), 4);
+        assert_eq!(string_width_with("ｶﾞ", &opts), 2);
+
+        // Full-width Latin letters, digits and punctuation take two
+        let latin = "ＡＢＣａｂｃ０１２！";
+        assert_eq!(string_width(latin), 2 * latin.chars().count());
+        assert_eq!(string_width("\u{3000}"), 2);
+
+        // The half-width symbol block goes back to one column
+        assert_eq!(string_width("￨￩￪"), 3);
+        for text in [kana, latin] {
+            assert_eq!(string_width_no_alloc(text), string_width(text));
+        }
+    }
+
+    #[test]
+    fn test_width_of_matches_stripped_string_width() {
+        let corpus = [
+            "",
+            "hello",
+            "\u{001B}[31m古古\u{001B}[0m x",
+            "e\u{0301}\u{0301} \u{200B}a",
+            "👨\u{200D}👩\u{200D}👧 ok",
+            "ab\u{0008}\u{0008}c\td",
+            "\u{0301}lead",
+        ];
+        for text in corpus {
+            let stripped = strip_ansi(text);
+            assert_eq!(width_of(stripped.chars()), string_width(&stripped), "{:?}", text);
+            // Backspaces survive when only the escapes are removed
+            assert_eq!(width_of(strip_escapes(text).chars()), string_width(text), "{:?}", text);
+        }
+
+        // Unstripped input counts the escape's parameters as text
+        assert_eq!(width_of("\u{001B}[1mx".chars()), 4);
+    }
 }